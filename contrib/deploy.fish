@@ -7,7 +7,7 @@ set -x IMAGE_SUFFIX :(date +%s)
 if test (count $argv) -gt 0
   set -x SHARD_COUNT $argv[1]
 else
-  set -x SHARD_COUNT (cargo run -p tulpje-manager)
+  set -x SHARD_COUNT (cargo run -p tulpje-manager -- recommended)
 end
 
 echo "* shard count: $SHARD_COUNT"
@@ -20,10 +20,12 @@ echo "* tagging images correctly..."
 docker tag discord-proxy$IMAGE_SUFFIX  $DOCKER_REPO/tulpje/discord-proxy$IMAGE_SUFFIX
 docker tag tulpje-handler$IMAGE_SUFFIX $DOCKER_REPO/tulpje/handler$IMAGE_SUFFIX
 docker tag tulpje-gateway$IMAGE_SUFFIX $DOCKER_REPO/tulpje/gateway$IMAGE_SUFFIX
+docker tag tulpje-manager$IMAGE_SUFFIX $DOCKER_REPO/tulpje/manager$IMAGE_SUFFIX
 docker tag gateway-queue$IMAGE_SUFFIX  $DOCKER_REPO/tulpje/gateway-queue$IMAGE_SUFFIX
 
 echo "* pushing images..."
 docker push $DOCKER_REPO/tulpje/discord-proxy$IMAGE_SUFFIX
 docker push $DOCKER_REPO/tulpje/handler$IMAGE_SUFFIX
 docker push $DOCKER_REPO/tulpje/gateway$IMAGE_SUFFIX
+docker push $DOCKER_REPO/tulpje/manager$IMAGE_SUFFIX
 docker push $DOCKER_REPO/tulpje/gateway-queue$IMAGE_SUFFIX
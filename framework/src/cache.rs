@@ -0,0 +1,217 @@
+use std::{future::Future, sync::Arc};
+
+use bb8_redis::{redis::AsyncCommands as _, RedisConnectionManager};
+use serde::de::DeserializeOwned;
+use twilight_http::Client;
+use twilight_model::{
+    channel::{Channel, Message},
+    guild::{Emoji, Guild, Member},
+    id::{
+        marker::{ChannelMarker, GuildMarker, MessageMarker, UserMarker},
+        Id,
+    },
+};
+
+use tulpje_shared::cache;
+
+use crate::Error;
+
+// reads the discord cache the gateway keeps in redis, see `tulpje_shared::cache`,
+// anything that isn't cached, or can't be read, is fetched from the http api, as
+// is everything when no gateway is keeping the cache up-to-date
+#[derive(Clone, Debug)]
+pub struct Cache {
+    redis: bb8::Pool<RedisConnectionManager>,
+    client: Arc<Client>,
+}
+
+impl Cache {
+    pub fn new(redis: bb8::Pool<RedisConnectionManager>, client: Arc<Client>) -> Self {
+        Self { redis, client }
+    }
+
+    pub async fn guild(&self, guild_id: Id<GuildMarker>) -> Result<Guild, Error> {
+        if let Some(guild) = self.hit(self.cached_guild(guild_id), "guild").await {
+            return Ok(guild);
+        }
+
+        Ok(self.client.guild(guild_id).await?.model().await?)
+    }
+
+    pub async fn guild_channels(&self, guild_id: Id<GuildMarker>) -> Result<Vec<Channel>, Error> {
+        let cached = self.cached_guild_hash(guild_id, cache::channels_key(guild_id));
+        if let Some(channels) = self.hit(cached, "guild channels").await {
+            return Ok(channels);
+        }
+
+        Ok(self.client.guild_channels(guild_id).await?.models().await?)
+    }
+
+    pub async fn emojis(&self, guild_id: Id<GuildMarker>) -> Result<Vec<Emoji>, Error> {
+        if let Some(emojis) = self.hit(self.cached_emojis(guild_id), "emojis").await {
+            return Ok(emojis);
+        }
+
+        Ok(self.client.emojis(guild_id).await?.model().await?)
+    }
+
+    // members are only cached when the gateway receives member updates, and
+    // only the ones it has seen, anything else is fetched
+    pub async fn member(
+        &self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+    ) -> Result<Member, Error> {
+        let cached = async {
+            let json_member = self
+                .redis
+                .get()
+                .await?
+                .hget::<String, String, Option<String>>(
+                    cache::members_key(guild_id),
+                    user_id.to_string(),
+                )
+                .await?;
+
+            parse(json_member)
+        };
+        if let Some(member) = self.hit(cached, "member").await {
+            return Ok(member);
+        }
+
+        Ok(self
+            .client
+            .guild_member(guild_id, user_id)
+            .await?
+            .model()
+            .await?)
+    }
+
+    pub async fn message(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+    ) -> Result<Message, Error> {
+        let cached = async {
+            let json_message = self
+                .redis
+                .get()
+                .await?
+                .get::<String, Option<String>>(cache::message_key(message_id))
+                .await?;
+
+            parse(json_message)
+        };
+        if let Some(message) = self.hit(cached, "message").await {
+            return Ok(message);
+        }
+
+        Ok(self
+            .client
+            .message(channel_id, message_id)
+            .await?
+            .model()
+            .await?)
+    }
+
+    async fn cached_guild(&self, guild_id: Id<GuildMarker>) -> Result<Option<Guild>, Error> {
+        let mut redis = self.redis.get().await?;
+
+        let Some(mut guild) = parse::<Guild>(
+            redis
+                .get::<String, Option<String>>(cache::guild_key(guild_id))
+                .await?,
+        )?
+        else {
+            return Ok(None);
+        };
+
+        // roles and emojis are kept in their own hashes
+        guild.roles = parse_all(
+            redis
+                .hvals::<String, Vec<String>>(cache::roles_key(guild_id))
+                .await?,
+        )?;
+        let Some(emojis) = self.cached_emojis(guild_id).await? else {
+            return Ok(None);
+        };
+        guild.emojis = emojis;
+
+        Ok(Some(guild))
+    }
+
+    // emojis are only cached for guilds that are cached, by gateways that
+    // receive emoji updates, the hash doesn't exist when a guild has no emojis
+    // so that's tracked separately
+    async fn cached_emojis(&self, guild_id: Id<GuildMarker>) -> Result<Option<Vec<Emoji>>, Error> {
+        let cached = self
+            .redis
+            .get()
+            .await?
+            .exists::<String, bool>(cache::emojis_cached_key(guild_id))
+            .await?;
+        if !cached {
+            return Ok(None);
+        }
+
+        self.cached_guild_hash(guild_id, cache::emojis_key(guild_id))
+            .await
+    }
+
+    // cache misses and errors both fall back to the http api, errors get logged
+    async fn hit<V>(
+        &self,
+        cached: impl Future<Output = Result<Option<V>, Error>>,
+        kind: &str,
+    ) -> Option<V> {
+        let cached = async {
+            if !self
+                .redis
+                .get()
+                .await?
+                .exists::<&str, bool>(cache::HEARTBEAT_KEY)
+                .await?
+            {
+                return Ok(None);
+            }
+
+            cached.await
+        };
+
+        cached.await.unwrap_or_else(|err| {
+            tracing::warn!("error reading {} from cache: {}", kind, err);
+            None
+        })
+    }
+
+    // the hashes are only complete for guilds that are cached, an empty hash
+    // could otherwise just mean we've never seen the guild
+    async fn cached_guild_hash<V: DeserializeOwned>(
+        &self,
+        guild_id: Id<GuildMarker>,
+        key: String,
+    ) -> Result<Option<Vec<V>>, Error> {
+        let mut redis = self.redis.get().await?;
+
+        if !redis
+            .exists::<String, bool>(cache::guild_key(guild_id))
+            .await?
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(parse_all(
+            redis.hvals::<String, Vec<String>>(key).await?,
+        )?))
+    }
+}
+
+fn parse<V: DeserializeOwned>(json: Option<String>) -> Result<Option<V>, Error> {
+    Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+}
+
+fn parse_all<V: DeserializeOwned>(json: Vec<String>) -> Result<Vec<V>, Error> {
+    json.into_iter()
+        .map(|json| serde_json::from_str(&json).map_err(Into::into))
+        .collect()
+}
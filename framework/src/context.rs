@@ -3,6 +3,8 @@ use std::sync::Arc;
 use twilight_http::{client::InteractionClient, Client};
 use twilight_model::id::{marker::ApplicationMarker, Id};
 
+use crate::cache::Cache;
+
 pub mod autocomplete_context;
 pub mod command_context;
 pub mod component_interaction_context;
@@ -10,6 +12,7 @@ pub mod event_context;
 pub mod modal_context;
 pub mod task_context;
 
+pub use autocomplete_context::AutocompleteContext;
 pub use command_context::CommandContext;
 pub use component_interaction_context::ComponentInteractionContext;
 pub use event_context::EventContext;
@@ -21,12 +24,17 @@ pub struct Context<T: Clone + Send + Sync> {
     pub application_id: Id<ApplicationMarker>,
     pub services: T,
     pub client: Arc<Client>,
+    pub cache: Cache,
 }
 
 impl<T: Clone + Send + Sync> Context<T> {
     pub fn interaction(&self) -> InteractionClient<'_> {
         self.client.interaction(self.application_id)
     }
+
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
 }
 
 impl<T: Clone + Send + Sync> Clone for Context<T> {
@@ -35,11 +43,13 @@ impl<T: Clone + Send + Sync> Clone for Context<T> {
             application_id: self.application_id,
             services: self.services.clone(),
             client: Arc::clone(&self.client),
+            cache: self.cache.clone(),
         }
     }
 }
 
 pub enum InteractionContext<T: Clone + Send + Sync> {
+    Autocomplete(AutocompleteContext<T>),
     Command(CommandContext<T>),
     ComponentInteraction(ComponentInteractionContext<T>),
     Modal(ModalContext<T>),
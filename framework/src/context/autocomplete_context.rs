@@ -1 +1,144 @@
+use std::sync::Arc;
 
+use tulpje_shared::DiscordEventMeta;
+use twilight_http::{client::InteractionClient, response::marker::EmptyBody, Client};
+use twilight_model::{
+    application::{
+        command::{CommandOptionChoice, CommandOptionChoiceValue},
+        interaction::application_command::{CommandData, CommandDataOption, CommandOptionValue},
+    },
+    gateway::payload::incoming::InteractionCreate,
+    guild::Guild,
+    http::interaction::{InteractionResponse, InteractionResponseType},
+    id::{marker::ApplicationMarker, Id},
+};
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+use super::{command_context::command_path, Context};
+use crate::{cache::Cache, options::OptionResolver, Error};
+
+// discord doesn't accept more than 25 autocomplete choices
+const MAX_CHOICES: usize = 25;
+
+#[derive(Clone, Debug)]
+pub struct AutocompleteContext<T: Clone + Send + Sync> {
+    pub meta: DiscordEventMeta,
+    pub application_id: Id<ApplicationMarker>,
+    pub services: T,
+    pub client: Arc<Client>,
+    pub cache: Cache,
+
+    pub event: InteractionCreate,
+    pub command: CommandData,
+
+    // name of the option the user is currently typing in
+    pub focused_option: String,
+    // what the user has typed so far for the focused option
+    pub focused_value: String,
+}
+
+impl<T: Clone + Send + Sync> AutocompleteContext<T> {
+    pub fn from_context(
+        meta: DiscordEventMeta,
+        ctx: Context<T>,
+        event: InteractionCreate,
+        command: CommandData,
+    ) -> Result<Self, Error> {
+        let (focused_option, focused_value) = find_focused(&command.options)
+            .ok_or_else(|| format!("no focused option for /{}", command.name))?;
+
+        Ok(Self {
+            meta,
+            application_id: ctx.application_id,
+            client: ctx.client,
+            cache: ctx.cache,
+            services: ctx.services,
+
+            command,
+            event,
+
+            focused_option,
+            focused_value,
+        })
+    }
+
+    // full path of the command being autocompleted, e.g. "pk fronters update"
+    pub fn path(&self) -> String {
+        command_path(&self.command)
+    }
+
+    // values of the other options the user has filled in so far
+    pub fn option_resolver(&self) -> OptionResolver<'_> {
+        OptionResolver::new(&self.command)
+    }
+
+    pub fn interaction(&self) -> InteractionClient<'_> {
+        self.client.interaction(self.application_id)
+    }
+
+    pub fn client(&self) -> Arc<Client> {
+        Arc::clone(&self.client)
+    }
+
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
+    pub async fn guild(&self) -> Result<Option<Guild>, Error> {
+        let Some(guild_id) = self.event.guild_id else {
+            return Ok(None);
+        };
+
+        Ok(Some(self.cache.guild(guild_id).await?))
+    }
+
+    pub async fn response(
+        &self,
+        response: InteractionResponse,
+    ) -> Result<twilight_http::Response<EmptyBody>, twilight_http::Error> {
+        self.interaction()
+            .create_response(self.event.id, &self.event.token, &response)
+            .await
+    }
+
+    pub async fn autocomplete(
+        &self,
+        choices: impl IntoIterator<Item = CommandOptionChoice>,
+    ) -> Result<twilight_http::Response<EmptyBody>, twilight_http::Error> {
+        self.response(InteractionResponse {
+            kind: InteractionResponseType::ApplicationCommandAutocompleteResult,
+            data: Some(
+                InteractionResponseDataBuilder::new()
+                    .choices(choices.into_iter().take(MAX_CHOICES))
+                    .build(),
+            ),
+        })
+        .await
+    }
+
+    pub async fn autocomplete_strings(
+        &self,
+        choices: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+    ) -> Result<twilight_http::Response<EmptyBody>, twilight_http::Error> {
+        self.autocomplete(
+            choices
+                .into_iter()
+                .map(|(name, value)| CommandOptionChoice {
+                    name: name.into(),
+                    name_localizations: None,
+                    value: CommandOptionChoiceValue::String(value.into()),
+                }),
+        )
+        .await
+    }
+}
+
+fn find_focused(options: &[CommandDataOption]) -> Option<(String, String)> {
+    options.iter().find_map(|opt| match &opt.value {
+        CommandOptionValue::Focused(value, _) => Some((opt.name.clone(), value.clone())),
+        CommandOptionValue::SubCommand(options) | CommandOptionValue::SubCommandGroup(options) => {
+            find_focused(options)
+        }
+        _ => None,
+    })
+}
@@ -3,17 +3,25 @@ use std::sync::Arc;
 use tulpje_shared::DiscordEventMeta;
 use twilight_http::{client::InteractionClient, response::marker::EmptyBody, Client};
 use twilight_model::{
-    application::interaction::application_command::{CommandData, CommandOptionValue},
-    channel::{message::MessageFlags, Message},
+    application::interaction::{
+        application_command::{CommandData, CommandDataOption, CommandOptionValue},
+        InteractionChannel,
+    },
+    channel::{message::MessageFlags, Attachment, Message},
     gateway::payload::incoming::InteractionCreate,
-    guild::Guild,
+    guild::{Guild, Role},
     http::interaction::{InteractionResponse, InteractionResponseType},
     id::{marker::ApplicationMarker, Id},
+    user::User,
 };
 use twilight_util::builder::InteractionResponseDataBuilder;
 
 use super::Context;
-use crate::Error;
+use crate::{
+    cache::Cache,
+    options::{CommandOptions, FromOption, Mentionable, OptionResolver},
+    Error,
+};
 
 #[derive(Clone, Debug)]
 pub struct CommandContext<T: Clone + Send + Sync> {
@@ -21,6 +29,7 @@ pub struct CommandContext<T: Clone + Send + Sync> {
     pub application_id: Id<ApplicationMarker>,
     pub services: T,
     pub client: Arc<Client>,
+    pub cache: Cache,
 
     pub event: InteractionCreate,
     pub command: CommandData,
@@ -37,6 +46,7 @@ impl<T: Clone + Send + Sync> CommandContext<T> {
             meta,
             application_id: ctx.application_id,
             client: ctx.client,
+            cache: ctx.cache,
             services: ctx.services,
 
             command,
@@ -52,12 +62,16 @@ impl<T: Clone + Send + Sync> CommandContext<T> {
         Arc::clone(&self.client)
     }
 
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
     pub async fn guild(&self) -> Result<Option<Guild>, Error> {
         let Some(guild_id) = self.event.guild_id else {
             return Ok(None);
         };
 
-        Ok(Some(self.client.guild(guild_id).await?.model().await?))
+        Ok(Some(self.cache.guild(guild_id).await?))
     }
 
     pub async fn response(
@@ -94,6 +108,22 @@ impl<T: Clone + Send + Sync> CommandContext<T> {
         .await
     }
 
+    pub async fn reply_ephemeral(
+        &self,
+        message: impl Into<String>,
+    ) -> Result<twilight_http::Response<EmptyBody>, twilight_http::Error> {
+        let response = InteractionResponseDataBuilder::new()
+            .content(message)
+            .flags(MessageFlags::EPHEMERAL)
+            .build();
+
+        self.response(InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(response),
+        })
+        .await
+    }
+
     pub async fn defer(&self) -> Result<twilight_http::Response<EmptyBody>, twilight_http::Error> {
         self.response(InteractionResponse {
             kind: InteractionResponseType::DeferredChannelMessageWithSource,
@@ -115,20 +145,134 @@ impl<T: Clone + Send + Sync> CommandContext<T> {
         .await
     }
 
-    pub fn get_arg_string_optional(&self, name: &str) -> Result<Option<String>, Error> {
-        let Some(opt) = self.command.options.iter().find(|opt| opt.name == name) else {
-            return Ok(None);
-        };
+    // full path of the invoked command, e.g. "pk fronters update"
+    pub fn path(&self) -> String {
+        command_path(&self.command)
+    }
 
-        let CommandOptionValue::String(value) = &opt.value else {
-            return Err(format!("option '{}' not a string option", name).into());
-        };
+    // options of the invoked (sub)command
+    pub fn options(&self) -> &[CommandDataOption] {
+        subcommand_options(&self.command.options)
+    }
+
+    pub fn option_resolver(&self) -> OptionResolver<'_> {
+        OptionResolver::new(&self.command)
+    }
+
+    // parses all options into a struct, see `command_options!`
+    pub fn parse_options<O: CommandOptions>(&self) -> Result<O, Error> {
+        self.option_resolver().parse()
+    }
+
+    pub fn get_arg_optional<V: FromOption>(&self, name: &str) -> Result<Option<V>, Error> {
+        self.option_resolver().get_optional(name)
+    }
 
-        Ok(Some(value.clone()))
+    pub fn get_arg<V: FromOption>(&self, name: &str) -> Result<V, Error> {
+        self.option_resolver().get(name)
+    }
+
+    pub fn get_arg_string_optional(&self, name: &str) -> Result<Option<String>, Error> {
+        self.get_arg_optional(name)
     }
 
     pub fn get_arg_string(&self, name: &str) -> Result<String, Error> {
-        self.get_arg_string_optional(name)?
-            .ok_or_else(|| format!("couldn't find command argument {}", name).into())
+        self.get_arg(name)
+    }
+
+    pub fn get_arg_integer_optional(&self, name: &str) -> Result<Option<i64>, Error> {
+        self.get_arg_optional(name)
+    }
+
+    pub fn get_arg_integer(&self, name: &str) -> Result<i64, Error> {
+        self.get_arg(name)
+    }
+
+    pub fn get_arg_number_optional(&self, name: &str) -> Result<Option<f64>, Error> {
+        self.get_arg_optional(name)
+    }
+
+    pub fn get_arg_number(&self, name: &str) -> Result<f64, Error> {
+        self.get_arg(name)
+    }
+
+    pub fn get_arg_boolean_optional(&self, name: &str) -> Result<Option<bool>, Error> {
+        self.get_arg_optional(name)
+    }
+
+    pub fn get_arg_boolean(&self, name: &str) -> Result<bool, Error> {
+        self.get_arg(name)
+    }
+
+    pub fn get_arg_user_optional(&self, name: &str) -> Result<Option<User>, Error> {
+        self.get_arg_optional(name)
+    }
+
+    pub fn get_arg_user(&self, name: &str) -> Result<User, Error> {
+        self.get_arg(name)
+    }
+
+    pub fn get_arg_channel_optional(
+        &self,
+        name: &str,
+    ) -> Result<Option<InteractionChannel>, Error> {
+        self.get_arg_optional(name)
+    }
+
+    pub fn get_arg_channel(&self, name: &str) -> Result<InteractionChannel, Error> {
+        self.get_arg(name)
+    }
+
+    pub fn get_arg_role_optional(&self, name: &str) -> Result<Option<Role>, Error> {
+        self.get_arg_optional(name)
+    }
+
+    pub fn get_arg_role(&self, name: &str) -> Result<Role, Error> {
+        self.get_arg(name)
+    }
+
+    pub fn get_arg_mentionable_optional(&self, name: &str) -> Result<Option<Mentionable>, Error> {
+        self.get_arg_optional(name)
+    }
+
+    pub fn get_arg_mentionable(&self, name: &str) -> Result<Mentionable, Error> {
+        self.get_arg(name)
+    }
+
+    pub fn get_arg_attachment_optional(&self, name: &str) -> Result<Option<Attachment>, Error> {
+        self.get_arg_optional(name)
+    }
+
+    pub fn get_arg_attachment(&self, name: &str) -> Result<Attachment, Error> {
+        self.get_arg(name)
+    }
+}
+
+pub(crate) fn command_path(command: &CommandData) -> String {
+    let mut path = vec![command.name.as_str()];
+
+    let mut options = command.options.as_slice();
+    while let Some(opt) = options.first() {
+        match &opt.value {
+            CommandOptionValue::SubCommand(nested)
+            | CommandOptionValue::SubCommandGroup(nested) => {
+                path.push(&opt.name);
+                options = nested;
+            }
+            _ => break,
+        }
+    }
+
+    path.join(" ")
+}
+
+// descends into subcommand groups and subcommands, they're always the
+// only option on their level
+pub(crate) fn subcommand_options(options: &[CommandDataOption]) -> &[CommandDataOption] {
+    match options.first().map(|opt| &opt.value) {
+        Some(
+            CommandOptionValue::SubCommand(nested) | CommandOptionValue::SubCommandGroup(nested),
+        ) => subcommand_options(nested),
+        _ => options,
     }
 }
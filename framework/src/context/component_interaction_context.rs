@@ -10,7 +10,7 @@ use twilight_model::{
     id::{marker::ApplicationMarker, Id},
 };
 
-use crate::Error;
+use crate::{cache::Cache, custom_id::ComponentState, Error};
 
 #[derive(Clone, Debug)]
 pub struct ComponentInteractionContext<T: Clone + Send + Sync> {
@@ -18,9 +18,12 @@ pub struct ComponentInteractionContext<T: Clone + Send + Sync> {
     pub application_id: Id<ApplicationMarker>,
     pub services: T,
     pub client: Arc<Client>,
+    pub cache: Cache,
 
     pub event: InteractionCreate,
     pub interaction: MessageComponentInteractionData,
+    // arguments following the prefix in the custom_id, `{prefix}:{arg1}:{arg2}:...`
+    pub args: Vec<String>,
 }
 
 impl<T: Clone + Send + Sync> ComponentInteractionContext<T> {
@@ -28,12 +31,20 @@ impl<T: Clone + Send + Sync> ComponentInteractionContext<T> {
         self.client.interaction(self.application_id)
     }
 
+    pub fn state<S: ComponentState>(&self) -> Result<S, Error> {
+        S::from_args(&self.args)
+    }
+
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
     pub async fn guild(&self) -> Result<Option<Guild>, Error> {
         let Some(guild_id) = self.event.guild_id else {
             return Ok(None);
         };
 
-        Ok(Some(self.client.guild(guild_id).await?.model().await?))
+        Ok(Some(self.cache.guild(guild_id).await?))
     }
 
     pub async fn response(
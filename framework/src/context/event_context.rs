@@ -5,12 +5,21 @@ use twilight_gateway::Event;
 use twilight_http::Client;
 use twilight_model::id::{marker::ApplicationMarker, Id};
 
+use crate::cache::Cache;
+
 #[derive(Clone, Debug)]
 pub struct EventContext<T: Clone + Send + Sync> {
     pub meta: DiscordEventMeta,
     pub application_id: Id<ApplicationMarker>,
     pub services: T,
     pub client: Arc<Client>,
+    pub cache: Cache,
 
     pub event: Event,
 }
+
+impl<T: Clone + Send + Sync> EventContext<T> {
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+}
@@ -1,21 +1,120 @@
 use std::sync::Arc;
 
-use twilight_http::Client;
+use twilight_http::{client::InteractionClient, response::marker::EmptyBody, Client};
 use twilight_model::{
     application::interaction::modal::ModalInteractionData,
+    channel::{message::MessageFlags, Message},
     gateway::payload::incoming::InteractionCreate,
+    guild::Guild,
+    http::interaction::{InteractionResponse, InteractionResponseType},
     id::{marker::ApplicationMarker, Id},
 };
+use twilight_util::builder::InteractionResponseDataBuilder;
 
 use tulpje_shared::DiscordEventMeta;
 
+use crate::{cache::Cache, Error};
+
 #[derive(Clone, Debug)]
 pub struct ModalContext<T: Clone + Send + Sync> {
     pub meta: DiscordEventMeta,
     pub application_id: Id<ApplicationMarker>,
     pub services: T,
     pub client: Arc<Client>,
+    pub cache: Cache,
 
     pub event: InteractionCreate,
     pub data: ModalInteractionData,
 }
+
+impl<T: Clone + Send + Sync> ModalContext<T> {
+    pub fn interaction(&self) -> InteractionClient<'_> {
+        self.client.interaction(self.application_id)
+    }
+
+    pub fn client(&self) -> Arc<Client> {
+        Arc::clone(&self.client)
+    }
+
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
+    pub async fn guild(&self) -> Result<Option<Guild>, Error> {
+        let Some(guild_id) = self.event.guild_id else {
+            return Ok(None);
+        };
+
+        Ok(Some(self.cache.guild(guild_id).await?))
+    }
+
+    pub async fn response(
+        &self,
+        response: InteractionResponse,
+    ) -> Result<twilight_http::Response<EmptyBody>, twilight_http::Error> {
+        self.interaction()
+            .create_response(self.event.id, &self.event.token, &response)
+            .await
+    }
+
+    pub async fn update(
+        &self,
+        message: impl Into<String>,
+    ) -> Result<twilight_http::Response<Message>, twilight_http::Error> {
+        self.interaction()
+            .update_response(&self.event.token)
+            .content(Some(&message.into()))
+            .await
+    }
+
+    pub async fn reply(
+        &self,
+        message: impl Into<String>,
+    ) -> Result<twilight_http::Response<EmptyBody>, twilight_http::Error> {
+        let response = InteractionResponseDataBuilder::new()
+            .content(message)
+            .build();
+
+        self.response(InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(response),
+        })
+        .await
+    }
+
+    pub async fn defer(&self) -> Result<twilight_http::Response<EmptyBody>, twilight_http::Error> {
+        self.response(InteractionResponse {
+            kind: InteractionResponseType::DeferredChannelMessageWithSource,
+            data: None,
+        })
+        .await
+    }
+
+    pub async fn defer_ephemeral(
+        &self,
+    ) -> Result<twilight_http::Response<EmptyBody>, twilight_http::Error> {
+        self.response(InteractionResponse {
+            kind: InteractionResponseType::DeferredChannelMessageWithSource,
+            data: Some(
+                InteractionResponseDataBuilder::new()
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            ),
+        })
+        .await
+    }
+
+    pub fn get_field_optional(&self, custom_id: &str) -> Option<String> {
+        self.data
+            .components
+            .iter()
+            .flat_map(|row| row.components.iter())
+            .find(|component| component.custom_id == custom_id)
+            .and_then(|component| component.value.clone())
+    }
+
+    pub fn get_field(&self, custom_id: &str) -> Result<String, Error> {
+        self.get_field_optional(custom_id)
+            .ok_or_else(|| format!("couldn't find modal field {}", custom_id).into())
+    }
+}
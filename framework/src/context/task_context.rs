@@ -3,12 +3,14 @@ use twilight_http::Client;
 use twilight_model::id::{marker::ApplicationMarker, Id};
 
 use super::Context;
+use crate::cache::Cache;
 
 #[derive(Debug)]
 pub struct TaskContext<T: Clone + Send + Sync> {
     pub application_id: Id<ApplicationMarker>,
     pub services: T,
     pub client: Arc<Client>,
+    pub cache: Cache,
 }
 
 impl<T: Clone + Send + Sync> TaskContext<T> {
@@ -17,6 +19,11 @@ impl<T: Clone + Send + Sync> TaskContext<T> {
             application_id: ctx.application_id,
             services: ctx.services,
             client: ctx.client,
+            cache: ctx.cache,
         }
     }
+
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
 }
@@ -0,0 +1,116 @@
+use crate::Error;
+
+// discord doesn't allow custom_ids longer than 100 characters
+pub const MAX_LENGTH: usize = 100;
+pub const SEPARATOR: char = ':';
+
+// state that can be round-tripped through a component's custom_id, the
+// custom_id will look like `{prefix}:{arg1}:{arg2}:...`
+pub trait ComponentState: Sized {
+    fn to_args(&self) -> Vec<String>;
+    fn from_args(args: &[String]) -> Result<Self, Error>;
+
+    fn to_custom_id(&self, prefix: &str) -> Result<String, Error> {
+        encode(prefix, &self.to_args())
+    }
+}
+
+pub fn encode(prefix: &str, args: &[String]) -> Result<String, Error> {
+    if let Some(arg) = args.iter().find(|arg| arg.contains(SEPARATOR)) {
+        return Err(format!("custom_id argument '{}' contains '{}'", arg, SEPARATOR).into());
+    }
+
+    let custom_id = std::iter::once(prefix)
+        .chain(args.iter().map(String::as_str))
+        .collect::<Vec<&str>>()
+        .join(&SEPARATOR.to_string());
+
+    if custom_id.len() > MAX_LENGTH {
+        return Err(format!(
+            "custom_id '{}' is longer than {} characters",
+            custom_id, MAX_LENGTH
+        )
+        .into());
+    }
+
+    Ok(custom_id)
+}
+
+pub fn decode(custom_id: &str) -> (&str, Vec<String>) {
+    match custom_id.split_once(SEPARATOR) {
+        Some((prefix, args)) => (
+            prefix,
+            args.split(SEPARATOR).map(ToString::to_string).collect(),
+        ),
+        None => (custom_id, Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct TestState {
+        sort: String,
+        page: u32,
+    }
+
+    impl ComponentState for TestState {
+        fn to_args(&self) -> Vec<String> {
+            vec![self.sort.clone(), self.page.to_string()]
+        }
+
+        fn from_args(args: &[String]) -> Result<Self, Error> {
+            let [sort, page] = args else {
+                return Err("invalid args".into());
+            };
+
+            Ok(Self {
+                sort: sort.clone(),
+                page: page.parse()?,
+            })
+        }
+    }
+
+    #[test]
+    fn decode_test() {
+        assert_eq!(decode("foo"), ("foo", vec![]));
+        assert_eq!(
+            decode("foo:bar:1"),
+            ("foo", vec![String::from("bar"), String::from("1")])
+        );
+    }
+
+    #[test]
+    fn encode_test() {
+        assert_eq!(encode("foo", &[]).unwrap(), "foo");
+        assert_eq!(
+            encode("foo", &[String::from("bar"), String::from("1")]).unwrap(),
+            "foo:bar:1"
+        );
+        assert!(
+            encode("foo", &[String::from("b:ar")]).is_err(),
+            "arguments containing the separator should be rejected"
+        );
+        assert!(
+            encode("foo", &["a".repeat(MAX_LENGTH)]).is_err(),
+            "custom_ids over the length limit should be rejected"
+        );
+    }
+
+    #[test]
+    fn component_state_round_trip_test() {
+        let state = TestState {
+            sort: String::from("count_desc"),
+            page: 3,
+        };
+
+        let custom_id = state.to_custom_id("emoji_stats").unwrap();
+        assert_eq!(custom_id, "emoji_stats:count_desc:3");
+
+        let (prefix, args) = decode(&custom_id);
+        assert_eq!(prefix, "emoji_stats");
+        assert_eq!(TestState::from_args(&args).unwrap(), state);
+    }
+}
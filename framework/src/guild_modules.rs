@@ -0,0 +1,90 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use twilight_model::id::{marker::GuildMarker, Id};
+
+use crate::Error;
+
+// per-guild module overrides, modules missing from the map use their default,
+// which is disabled for guild scoped modules and enabled for global ones
+pub type ModuleStates = HashMap<String, bool>;
+
+// looks up which modules are enabled for a guild, implemented by the bot so
+// the framework doesn't need to know where that's stored
+pub trait GuildModuleLookup: Send + Sync {
+    fn module_states(
+        &self,
+        guild_id: Id<GuildMarker>,
+    ) -> Pin<Box<dyn Future<Output = Result<ModuleStates, Error>> + Send + '_>>;
+
+    // called when a guild's modules have changed
+    fn invalidate(&self, _guild_id: Id<GuildMarker>) {}
+}
+
+// caches another lookup in memory, every event for a guild needs this so we
+// don't want to hit the database each time, changes made by other handlers
+// are picked up once the ttl expires
+pub struct CachedGuildModuleLookup<L: GuildModuleLookup> {
+    inner: L,
+    ttl: Duration,
+    cache: Mutex<HashMap<Id<GuildMarker>, (Instant, ModuleStates)>>,
+}
+
+impl<L: GuildModuleLookup> CachedGuildModuleLookup<L> {
+    pub fn new(inner: L, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn cached(&self, guild_id: Id<GuildMarker>) -> Option<ModuleStates> {
+        let cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+
+        cache
+            .get(&guild_id)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.ttl)
+            .map(|(_, states)| states.clone())
+    }
+
+    // drops expired entries while we're at it, so guilds we stopped getting
+    // events for don't stay cached forever
+    fn store(&self, guild_id: Id<GuildMarker>, states: ModuleStates) {
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+
+        cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < self.ttl);
+        cache.insert(guild_id, (Instant::now(), states));
+    }
+}
+
+impl<L: GuildModuleLookup> GuildModuleLookup for CachedGuildModuleLookup<L> {
+    fn module_states(
+        &self,
+        guild_id: Id<GuildMarker>,
+    ) -> Pin<Box<dyn Future<Output = Result<ModuleStates, Error>> + Send + '_>> {
+        Box::pin(async move {
+            if let Some(states) = self.cached(guild_id) {
+                return Ok(states);
+            }
+
+            let states = self.inner.module_states(guild_id).await?;
+            self.store(guild_id, states.clone());
+
+            Ok(states)
+        })
+    }
+
+    fn invalidate(&self, guild_id: Id<GuildMarker>) {
+        self.cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&guild_id);
+        self.inner.invalidate(guild_id);
+    }
+}
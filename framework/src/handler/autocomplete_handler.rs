@@ -1 +1,32 @@
+use std::{future::Future, pin::Pin};
 
+use super::super::context::AutocompleteContext;
+use super::InteractionHandler;
+use crate::{
+    metrics::{self, Kind},
+    Error,
+};
+
+pub(crate) type AutocompleteFunc<T> =
+    fn(AutocompleteContext<T>) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+
+#[derive(Clone)]
+pub struct AutocompleteHandler<T: Clone + Send + Sync> {
+    pub module: String,
+    pub command: String,
+    pub option: String,
+    pub func: AutocompleteFunc<T>,
+}
+
+impl<T: Clone + Send + Sync> InteractionHandler<(String, String)> for AutocompleteHandler<T> {
+    fn key(&self) -> (String, String) {
+        (self.command.clone(), self.option.clone())
+    }
+}
+
+impl<T: Clone + Send + Sync> AutocompleteHandler<T> {
+    pub async fn run(&self, ctx: AutocompleteContext<T>) -> Result<(), Error> {
+        let name = format!("{} {}", self.command, self.option);
+        metrics::track(Kind::Autocomplete, &self.module, &name, (self.func)(ctx)).await
+    }
+}
@@ -1,11 +1,13 @@
 use std::{future::Future, pin::Pin};
 
-use twilight_model::application::command::Command;
-
 use super::super::context::CommandContext;
 
 use super::InteractionHandler;
-use crate::Error;
+use crate::{
+    metrics::{self, Kind},
+    precondition::Precondition,
+    Error, Registry,
+};
 
 pub(crate) type CommandFunc<T> =
     fn(CommandContext<T>) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
@@ -13,19 +15,41 @@ pub(crate) type CommandFunc<T> =
 #[derive(Clone)]
 pub struct CommandHandler<T: Clone + Send + Sync> {
     pub module: String,
-    pub definition: Command,
+    // full path of the command, e.g. "pk fronters update" for subcommands
+    pub name: String,
+    pub preconditions: Vec<Precondition>,
     pub func: CommandFunc<T>,
 }
 
 impl<T: Clone + Send + Sync> InteractionHandler<String> for CommandHandler<T> {
     fn key(&self) -> String {
-        self.definition.name.clone()
+        self.name.clone()
     }
 }
 
 impl<T: Clone + Send + Sync> CommandHandler<T> {
+    // returns a user facing reason for the first precondition that isn't met
+    pub(crate) async fn check_preconditions(
+        &self,
+        ctx: &CommandContext<T>,
+        registry: &Registry<T>,
+    ) -> Result<(), String> {
+        if ctx.event.guild_id.is_some() {
+            Precondition::ModuleEnabled
+                .check(&self.module, &ctx.event, registry)
+                .await?;
+        }
+
+        for precondition in &self.preconditions {
+            precondition
+                .check(&self.module, &ctx.event, registry)
+                .await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn run(&self, ctx: CommandContext<T>) -> Result<(), Error> {
-        // can add more handling/parsing/etc here in the future
-        (self.func)(ctx).await
+        metrics::track(Kind::Command, &self.module, &self.name, (self.func)(ctx)).await
     }
 }
@@ -2,7 +2,10 @@ use std::{future::Future, pin::Pin};
 
 use super::super::context::ComponentInteractionContext;
 use super::InteractionHandler;
-use crate::Error;
+use crate::{
+    metrics::{self, Kind},
+    Error,
+};
 
 pub(crate) type ComponentInteractionFunc<T> =
     fn(ComponentInteractionContext<T>) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
@@ -22,7 +25,12 @@ impl<T: Clone + Send + Sync> InteractionHandler<String> for ComponentInteraction
 
 impl<T: Clone + Send + Sync> ComponentInteractionHandler<T> {
     pub async fn run(&self, ctx: ComponentInteractionContext<T>) -> Result<(), Error> {
-        // can add more handling/parsing/etc here in the future
-        (self.func)(ctx).await
+        metrics::track(
+            Kind::Component,
+            &self.module,
+            &self.custom_id,
+            (self.func)(ctx),
+        )
+        .await
     }
 }
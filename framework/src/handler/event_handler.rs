@@ -4,7 +4,10 @@ use std::{future::Future, pin::Pin};
 use twilight_gateway::EventType;
 
 use super::super::context::EventContext;
-use crate::Error;
+use crate::{
+    metrics::{self, Kind},
+    Error,
+};
 
 pub(crate) type EventFunc<T> =
     fn(EventContext<T>) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
@@ -12,15 +15,24 @@ pub(crate) type EventFunc<T> =
 #[derive(Clone)]
 pub struct EventHandler<T: Clone + Send + Sync> {
     pub module: String,
+    // unlike the uuid it's the same in every handler instance, so failed
+    // handlers can be retried by another instance
+    pub id: String,
     pub uuid: String,
     pub event: EventType,
     pub func: EventFunc<T>,
 }
 
+// `n` is the number of handlers the module already has for the event, modules
+// are built the same way in every instance so this gives them the same id
+pub(crate) fn handler_id(module: &str, event: EventType, n: usize) -> String {
+    format!("{}/{}/{}", module, event.name().unwrap_or("UNKNOWN"), n)
+}
+
 impl<T: Clone + Send + Sync> EventHandler<T> {
     pub async fn run(&self, ctx: EventContext<T>) -> Result<(), Error> {
-        // can add more handling/parsing/etc here in the future
-        (self.func)(ctx).await
+        let name = self.event.name().unwrap_or("UNKNOWN");
+        metrics::track(Kind::Event, &self.module, name, (self.func)(ctx)).await
     }
 }
 
@@ -1 +1,30 @@
+use std::{future::Future, pin::Pin};
 
+use super::super::context::ModalContext;
+use super::InteractionHandler;
+use crate::{
+    metrics::{self, Kind},
+    Error,
+};
+
+pub(crate) type ModalFunc<T> =
+    fn(ModalContext<T>) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+
+#[derive(Clone)]
+pub struct ModalHandler<T: Clone + Send + Sync> {
+    pub module: String,
+    pub custom_id: String,
+    pub func: ModalFunc<T>,
+}
+
+impl<T: Clone + Send + Sync> InteractionHandler<String> for ModalHandler<T> {
+    fn key(&self) -> String {
+        self.custom_id.clone()
+    }
+}
+
+impl<T: Clone + Send + Sync> ModalHandler<T> {
+    pub async fn run(&self, ctx: ModalContext<T>) -> Result<(), Error> {
+        metrics::track(Kind::Modal, &self.module, &self.custom_id, (self.func)(ctx)).await
+    }
+}
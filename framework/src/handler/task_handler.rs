@@ -4,6 +4,7 @@ use async_cron_scheduler::cron::Schedule;
 use chrono::{DateTime, Utc};
 
 use crate::context::TaskContext;
+use crate::metrics::{self, Kind};
 use crate::Error;
 
 pub(crate) type TaskFunc<T> =
@@ -19,8 +20,7 @@ pub struct TaskHandler<T: Clone + Send + Sync> {
 
 impl<T: Clone + Send + Sync> TaskHandler<T> {
     pub async fn run(&self, ctx: TaskContext<T>) -> Result<(), Error> {
-        // can add more handling/parsing/etc here in the future
-        (self.func)(ctx).await
+        metrics::track(Kind::Task, &self.module, &self.name, (self.func)(ctx)).await
     }
 
     pub fn next_run(&self) -> Option<DateTime<Utc>> {
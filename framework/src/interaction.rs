@@ -1,10 +1,12 @@
 use tulpje_shared::DiscordEventMeta;
 use twilight_model::{
-    application::interaction::InteractionData, gateway::payload::incoming::InteractionCreate,
+    application::interaction::{InteractionData, InteractionType},
+    gateway::payload::incoming::InteractionCreate,
 };
 
 use super::context;
 use crate::context::Context;
+use crate::custom_id;
 use crate::Error;
 
 pub fn parse<T: Clone + Send + Sync>(
@@ -13,21 +15,38 @@ pub fn parse<T: Clone + Send + Sync>(
     ctx: Context<T>,
 ) -> Result<context::InteractionContext<T>, Error> {
     match &event.data {
+        // autocomplete interactions share their data with application commands
+        Some(InteractionData::ApplicationCommand(command))
+            if event.kind == InteractionType::ApplicationCommandAutocomplete =>
+        {
+            Ok(context::InteractionContext::<T>::Autocomplete(
+                context::AutocompleteContext::from_context(
+                    meta,
+                    ctx,
+                    event.clone(),
+                    *command.clone(),
+                )?,
+            ))
+        }
         Some(InteractionData::ApplicationCommand(command)) => {
             Ok(context::InteractionContext::<T>::Command(
                 context::CommandContext::from_context(meta, ctx, event.clone(), *command.clone()),
             ))
         }
         Some(InteractionData::MessageComponent(interaction)) => {
+            let (_, args) = custom_id::decode(&interaction.custom_id);
+
             Ok(context::InteractionContext::<T>::ComponentInteraction(
                 context::ComponentInteractionContext {
                     meta,
                     application_id: ctx.application_id,
                     client: ctx.client,
+                    cache: ctx.cache,
                     services: ctx.services,
 
                     interaction: *interaction.clone(),
                     event: event.clone(),
+                    args,
                 },
             ))
         }
@@ -36,6 +55,7 @@ pub fn parse<T: Clone + Send + Sync>(
                 meta,
                 application_id: ctx.application_id,
                 client: ctx.client,
+                cache: ctx.cache,
                 services: ctx.services,
 
                 data: data.clone(),
@@ -1,22 +1,54 @@
 use std::sync::Arc;
 
 use tulpje_shared::DiscordEventMeta;
-use twilight_gateway::Event;
-use twilight_model::gateway::payload::incoming::InteractionCreate;
+use twilight_gateway::{Event, EventType};
+use twilight_model::{
+    gateway::payload::incoming::InteractionCreate,
+    id::{marker::GuildMarker, Id},
+};
 
 pub use context::{Context, EventContext, InteractionContext};
 pub use module::{builder::ModuleBuilder, registry::Registry, Module};
+pub use precondition::Precondition;
 pub use scheduler::Scheduler;
 
+pub mod cache;
 pub mod context;
+pub mod custom_id;
+pub mod guild_modules;
 pub mod handler;
 pub mod interaction;
 pub mod macros;
+pub mod metrics;
 pub mod module;
+pub mod module_config;
+pub mod options;
+pub mod precondition;
 pub mod scheduler;
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 
+// the ids of the event handlers that failed for an event, so just those can be
+// retried, the ids are the same across handler instances
+#[derive(Debug)]
+pub struct FailedHandlers {
+    pub event: EventType,
+    pub ids: Vec<String>,
+}
+
+impl std::fmt::Display for FailedHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} event handler(s) failed for {:?}",
+            self.ids.len(),
+            self.event
+        )
+    }
+}
+
+impl std::error::Error for FailedHandlers {}
+
 pub async fn handle_interaction<T: Clone + Send + Sync + 'static>(
     event: InteractionCreate,
     context: Context<T>,
@@ -26,17 +58,52 @@ pub async fn handle_interaction<T: Clone + Send + Sync + 'static>(
     tracing::info!("interaction");
 
     match interaction::parse(&event, meta.clone(), context) {
+        Ok(InteractionContext::Autocomplete(ctx)) => {
+            let path = ctx.path();
+            let Some(autocomplete) = registry.find_autocomplete(&path, &ctx.focused_option) else {
+                return Err(format!(
+                    "no autocomplete handler for /{} {}",
+                    path, ctx.focused_option
+                )
+                .into());
+            };
+
+            if !module_enabled(registry, ctx.event.guild_id, &autocomplete.module).await {
+                return Ok(());
+            }
+
+            if let Err(err) = autocomplete.run(ctx.clone()).await {
+                return Err(format!(
+                    "error running autocomplete for /{} {}: {}",
+                    path, ctx.focused_option, err
+                )
+                .into());
+            }
+        }
         Ok(InteractionContext::Command(ctx)) => {
-            let Some(command) = registry.find_command(&ctx.command.name) else {
-                return Err(format!("unknown command /{}", ctx.command.name).into());
+            let path = ctx.path();
+            let Some(command) = registry.find_command(&path) else {
+                return Err(format!("unknown command /{}", path).into());
             };
 
+            if let Err(reason) = command.check_preconditions(&ctx, registry).await {
+                tracing::debug!(command = %path, %reason, "precondition failed");
+                metrics::record(
+                    metrics::Kind::Command,
+                    &command.module,
+                    &path,
+                    "precondition_failed",
+                );
+                ctx.reply_ephemeral(format!("error: {}", reason)).await?;
+                return Ok(());
+            }
+
             if let Err(err) = command.run(ctx.clone()).await {
-                return Err(format!("error running command /{}: {}", ctx.command.name, err).into());
+                return Err(format!("error running command /{}: {}", path, err).into());
             }
         }
         Ok(InteractionContext::ComponentInteraction(ctx)) => {
-            let Some(component_interaction) = registry.components.get(&ctx.interaction.custom_id)
+            let Some(component_interaction) = registry.find_component(&ctx.interaction.custom_id)
             else {
                 return Err(format!(
                     "no handler for component interaction {}",
@@ -45,6 +112,10 @@ pub async fn handle_interaction<T: Clone + Send + Sync + 'static>(
                 .into());
             };
 
+            if !module_enabled(registry, ctx.event.guild_id, &component_interaction.module).await {
+                return Ok(());
+            }
+
             if let Err(err) = component_interaction.run(ctx.clone()).await {
                 return Err(format!(
                     "error handling component interaction {}: {}",
@@ -53,8 +124,18 @@ pub async fn handle_interaction<T: Clone + Send + Sync + 'static>(
                 .into());
             }
         }
-        Ok(InteractionContext::Modal(_modal_context)) => {
-            todo!()
+        Ok(InteractionContext::Modal(ctx)) => {
+            let Some(modal) = registry.modals.get(&ctx.data.custom_id) else {
+                return Err(format!("no handler for modal {}", ctx.data.custom_id).into());
+            };
+
+            if !module_enabled(registry, ctx.event.guild_id, &modal.module).await {
+                return Ok(());
+            }
+
+            if let Err(err) = modal.run(ctx.clone()).await {
+                return Err(format!("error handling modal {}: {}", ctx.data.custom_id, err).into());
+            }
         }
         Err(err) => return Err(format!("error handling interaction: {}", err).into()),
     };
@@ -62,13 +143,50 @@ pub async fn handle_interaction<T: Clone + Send + Sync + 'static>(
     Ok(())
 }
 
+// whether handlers of a module should run for the guild an interaction or
+// event came from, if the lookup fails we err on the side of not running them
+async fn module_enabled<T: Clone + Send + Sync>(
+    registry: &Registry<T>,
+    guild_id: Option<Id<GuildMarker>>,
+    module: &str,
+) -> bool {
+    let Some(guild_id) = guild_id else {
+        return true;
+    };
+
+    match registry.module_enabled(guild_id, module).await {
+        Ok(enabled) => {
+            if !enabled {
+                tracing::debug!(
+                    guild_id = guild_id.get(),
+                    module,
+                    "module disabled for guild"
+                );
+            }
+            enabled
+        }
+        Err(err) => {
+            tracing::warn!(
+                guild_id = guild_id.get(),
+                module,
+                "error looking up guild modules: {}",
+                err
+            );
+            false
+        }
+    }
+}
+
+// errors with the event handlers that failed, so the caller can retry them
 pub async fn handle<T: Clone + Send + Sync + 'static>(
     meta: DiscordEventMeta,
     ctx: Context<T>,
     registry: &Registry<T>,
     event: Event,
-) {
+) -> Result<(), FailedHandlers> {
     match event.clone() {
+        // interaction errors aren't returned, discord wants a response within 3
+        // seconds so retrying them isn't useful
         twilight_gateway::Event::InteractionCreate(event) => {
             if let Err(err) = handle_interaction(*event, ctx.clone(), &meta, registry).await {
                 tracing::warn!(err);
@@ -78,22 +196,64 @@ pub async fn handle<T: Clone + Send + Sync + 'static>(
         e => tracing::warn!(event = ?e.kind(), "unhandled event"),
     }
 
+    run_event_handlers(meta, &ctx, registry, event, None).await
+}
+
+// runs only the event handlers that failed, handlers that succeeded aren't run
+// twice
+pub async fn retry<T: Clone + Send + Sync + 'static>(
+    meta: DiscordEventMeta,
+    ctx: Context<T>,
+    registry: &Registry<T>,
+    event: Event,
+    failed: &FailedHandlers,
+) -> Result<(), FailedHandlers> {
+    run_event_handlers(meta, &ctx, registry, event, Some(failed.ids.as_slice())).await
+}
+
+async fn run_event_handlers<T: Clone + Send + Sync + 'static>(
+    meta: DiscordEventMeta,
+    ctx: &Context<T>,
+    registry: &Registry<T>,
+    event: Event,
+    only: Option<&[String]>,
+) -> Result<(), FailedHandlers> {
+    let mut failed = Vec::new();
     if let Some(handlers) = registry.events.get(&event.kind()) {
         tracing::info!("running event handlers for {:?}", event.kind());
 
         for handler in handlers {
+            if only.is_some_and(|only| !only.contains(&handler.id)) {
+                continue;
+            }
+
+            if !module_enabled(registry, event.guild_id(), &handler.module).await {
+                continue;
+            }
+
             let event_ctx = EventContext {
                 meta: meta.clone(),
                 application_id: ctx.application_id,
                 client: Arc::clone(&ctx.client),
+                cache: ctx.cache.clone(),
                 services: ctx.services.clone(),
 
                 event: event.clone(),
             };
 
             if let Err(err) = handler.run(event_ctx).await {
-                tracing::warn!("error running event handler {}: {}", handler.uuid, err);
+                tracing::warn!("error running event handler {}: {}", handler.id, err);
+                failed.push(handler.id.clone());
             }
         }
     }
+
+    if !failed.is_empty() {
+        return Err(FailedHandlers {
+            event: event.kind(),
+            ids: failed,
+        });
+    }
+
+    Ok(())
 }
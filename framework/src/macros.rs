@@ -4,3 +4,34 @@ macro_rules! handler_func {
         |ctx| Box::pin($func(ctx))
     };
 }
+
+// declares a struct and implements `CommandOptions` for it, each field is
+// parsed from the command option with the same name, `Option<T>` fields are
+// optional
+#[macro_export]
+macro_rules! command_options {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $ty),*
+        }
+
+        impl $crate::options::CommandOptions for $name {
+            fn from_options(
+                options: &$crate::options::OptionResolver<'_>,
+            ) -> Result<Self, $crate::Error> {
+                Ok(Self {
+                    $($field: $crate::options::OptionField::from_resolver(
+                        options,
+                        stringify!($field),
+                    )?),*
+                })
+            }
+        }
+    };
+}
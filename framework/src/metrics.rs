@@ -0,0 +1,105 @@
+use std::{future::Future, time::Instant};
+
+use metrics::{
+    counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit,
+};
+
+use crate::Error;
+
+// prometheus exporters export histograms as summaries unless buckets are set,
+// use these for `handler_duration_seconds`
+pub const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+pub fn describe() {
+    describe_counter!(
+        "handler_runs",
+        "Handler runs by kind, module, name and outcome"
+    );
+    describe_histogram!(
+        "handler_duration_seconds",
+        Unit::Seconds,
+        "Time spent running handlers"
+    );
+    describe_gauge!("handler_in_flight", "Handlers currently running");
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Kind {
+    Command,
+    Autocomplete,
+    Component,
+    Modal,
+    Event,
+    Task,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Command => "command",
+            Self::Autocomplete => "autocomplete",
+            Self::Component => "component",
+            Self::Modal => "modal",
+            Self::Event => "event",
+            Self::Task => "task",
+        }
+    }
+}
+
+// keeps a handler counted as in-flight until dropped, so handlers that panic
+// or get cancelled don't stay counted
+struct InFlight(Kind);
+
+impl InFlight {
+    fn new(kind: Kind) -> Self {
+        gauge!("handler_in_flight", "kind" => kind.as_str()).increment(1);
+        Self(kind)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        gauge!("handler_in_flight", "kind" => self.0.as_str()).decrement(1);
+    }
+}
+
+// runs a handler, recording how long it took and whether it succeeded
+pub(crate) async fn track(
+    kind: Kind,
+    module: &str,
+    name: &str,
+    handler: impl Future<Output = Result<(), Error>>,
+) -> Result<(), Error> {
+    let _in_flight = InFlight::new(kind);
+    let start = Instant::now();
+
+    let result = handler.await;
+    let outcome = if result.is_ok() { "success" } else { "error" };
+
+    histogram!(
+        "handler_duration_seconds",
+        "kind" => kind.as_str(),
+        "module" => module.to_string(),
+        "name" => name.to_string(),
+        "outcome" => outcome
+    )
+    .record(start.elapsed().as_secs_f64());
+    record(kind, module, name, outcome);
+
+    result
+}
+
+// counts a handler run, also used for runs that never reach the handler
+// e.g. because of a failed precondition
+pub(crate) fn record(kind: Kind, module: &str, name: &str, outcome: &'static str) {
+    counter!(
+        "handler_runs",
+        "kind" => kind.as_str(),
+        "module" => module.to_string(),
+        "name" => name.to_string(),
+        "outcome" => outcome
+    )
+    .increment(1);
+}
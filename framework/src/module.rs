@@ -1,11 +1,14 @@
 use std::collections::{HashMap, HashSet};
 
 use twilight_gateway::EventType;
+use twilight_model::application::command::Command;
 
 use crate::handler::{
-    command_handler::CommandHandler, component_interaction_handler::ComponentInteractionHandler,
-    event_handler::EventHandler, task_handler::TaskHandler,
+    autocomplete_handler::AutocompleteHandler, command_handler::CommandHandler,
+    component_interaction_handler::ComponentInteractionHandler, event_handler::EventHandler,
+    modal_handler::ModalHandler, task_handler::TaskHandler,
 };
+use crate::module_config::ConfigSchema;
 
 pub mod builder;
 pub mod registry;
@@ -14,9 +17,14 @@ pub mod registry;
 pub struct Module<T: Clone + Send + Sync> {
     pub(crate) name: String,
     pub(crate) guild_scoped: bool,
+    pub(crate) guild_toggleable: bool,
+    pub(crate) config: Option<ConfigSchema>,
 
+    pub(crate) definitions: HashMap<String, Command>,
     pub(crate) commands: HashMap<String, CommandHandler<T>>,
+    pub(crate) autocompletes: HashMap<(String, String), AutocompleteHandler<T>>,
     pub(crate) components: HashMap<String, ComponentInteractionHandler<T>>,
+    pub(crate) modals: HashMap<String, ModalHandler<T>>,
     pub(crate) events: HashMap<EventType, HashSet<EventHandler<T>>>,
     pub(crate) tasks: HashMap<String, TaskHandler<T>>,
 }
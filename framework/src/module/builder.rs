@@ -2,24 +2,40 @@ use std::collections::{HashMap, HashSet};
 
 use async_cron_scheduler::cron::Schedule;
 use twilight_gateway::EventType;
-use twilight_model::application::command::Command;
+use twilight_model::application::command::{Command, CommandOption, CommandOptionType};
 
 use super::Module;
 use crate::handler::{
+    autocomplete_handler::{AutocompleteFunc, AutocompleteHandler},
     command_handler::{CommandFunc, CommandHandler},
     component_interaction_handler::{ComponentInteractionFunc, ComponentInteractionHandler},
-    event_handler::{EventFunc, EventHandler},
+    event_handler::{handler_id, EventFunc, EventHandler},
+    modal_handler::{ModalFunc, ModalHandler},
     task_handler::{TaskFunc, TaskHandler},
 };
+use crate::{
+    custom_id,
+    module_config::{ConfigSchema, ModuleConfig},
+    precondition::Precondition,
+};
 
 pub struct ModuleBuilder<T: Clone + Send + Sync> {
     name: String,
     guild_scoped: bool,
+    guild_toggleable: bool,
+    config: Option<ConfigSchema>,
 
+    definitions: HashMap<String, Command>,
     commands: HashMap<String, CommandHandler<T>>,
+    autocompletes: HashMap<(String, String), AutocompleteHandler<T>>,
     components: HashMap<String, ComponentInteractionHandler<T>>,
+    modals: HashMap<String, ModalHandler<T>>,
     events: HashMap<EventType, HashSet<EventHandler<T>>>,
     tasks: HashMap<String, TaskHandler<T>>,
+
+    // path and preconditions, applied in `build()` so it doesn't matter
+    // whether they're added before or after the commands
+    preconditions: Vec<(String, Vec<Precondition>)>,
 }
 
 impl<T: Clone + Send + Sync> ModuleBuilder<T> {
@@ -27,22 +43,47 @@ impl<T: Clone + Send + Sync> ModuleBuilder<T> {
         Self {
             name: name.into(),
             guild_scoped: false,
+            guild_toggleable: false,
+            config: None,
 
+            definitions: HashMap::new(),
             commands: HashMap::new(),
+            autocompletes: HashMap::new(),
             components: HashMap::new(),
+            modals: HashMap::new(),
             events: HashMap::new(),
             tasks: HashMap::new(),
+
+            preconditions: Vec::new(),
         }
     }
 
     #[must_use]
-    pub fn build(self) -> Module<T> {
+    pub fn build(mut self) -> Module<T> {
+        for (path, preconditions) in &self.preconditions {
+            let prefix = format!("{} ", path);
+
+            let mut found = false;
+            for (name, handler) in &mut self.commands {
+                if name == path || name.starts_with(&prefix) {
+                    handler.preconditions.extend(preconditions.iter().copied());
+                    found = true;
+                }
+            }
+            assert!(found, "no commands registered for '{}'", path);
+        }
+
         Module {
             name: self.name,
             guild_scoped: self.guild_scoped,
+            guild_toggleable: self.guild_toggleable,
+            config: self.config,
 
+            definitions: self.definitions,
             commands: self.commands,
+            autocompletes: self.autocompletes,
             components: self.components,
+            modals: self.modals,
             events: self.events,
             tasks: self.tasks,
         }
@@ -54,13 +95,97 @@ impl<T: Clone + Send + Sync> ModuleBuilder<T> {
         self
     }
 
+    // lets guilds disable a global module, guild scoped modules always need to
+    // be enabled per guild
+    #[must_use]
+    pub fn toggleable(mut self) -> Self {
+        self.guild_toggleable = true;
+        self
+    }
+
+    // per-guild settings for this module, changeable through /config
+    #[must_use]
+    pub fn config<C: ModuleConfig>(mut self) -> Self {
+        self.config = Some(ConfigSchema::new::<C>());
+        self
+    }
+
     #[must_use]
     pub fn command(mut self, definition: Command, func: CommandFunc<T>) -> Self {
+        let name = definition.name.clone();
+
+        self.definitions.insert(name.clone(), definition);
         self.commands.insert(
-            definition.name.clone(),
+            name.clone(),
             CommandHandler {
                 module: self.name.clone(),
-                definition,
+                name,
+                preconditions: Vec::new(),
+                func,
+            },
+        );
+        self
+    }
+
+    // registers a command that consists of subcommands (and/or subcommand groups),
+    // discord doesn't allow invoking the base command itself so the handlers
+    // are registered with `subcommand()` instead
+    #[must_use]
+    pub fn command_tree(mut self, definition: Command) -> Self {
+        self.definitions.insert(definition.name.clone(), definition);
+        self
+    }
+
+    #[must_use]
+    pub fn subcommand(mut self, path: &str, func: CommandFunc<T>) -> Self {
+        let mut names = path.split(' ');
+        let command = names.next().unwrap_or_default();
+        let subcommand = names.collect::<Vec<&str>>();
+
+        let definition = self
+            .definitions
+            .get(command)
+            .unwrap_or_else(|| panic!("no command tree registered for '{}'", path));
+        assert!(
+            has_subcommand(&definition.options, &subcommand),
+            "command '{}' doesn't define subcommand '{}'",
+            command,
+            path
+        );
+
+        self.commands.insert(
+            path.to_string(),
+            CommandHandler {
+                module: self.name.clone(),
+                name: path.to_string(),
+                preconditions: Vec::new(),
+                func,
+            },
+        );
+        self
+    }
+
+    // adds preconditions to a command, for command trees this applies them to
+    // all subcommands under `path`, e.g. "pk" or "pk fronters"
+    #[must_use]
+    pub fn preconditions(
+        mut self,
+        path: &str,
+        preconditions: impl IntoIterator<Item = Precondition>,
+    ) -> Self {
+        self.preconditions
+            .push((path.to_string(), preconditions.into_iter().collect()));
+        self
+    }
+
+    #[must_use]
+    pub fn autocomplete(mut self, command: &str, option: &str, func: AutocompleteFunc<T>) -> Self {
+        self.autocompletes.insert(
+            (command.to_string(), option.to_string()),
+            AutocompleteHandler {
+                module: self.name.clone(),
+                command: command.to_string(),
+                option: option.to_string(),
                 func,
             },
         );
@@ -69,6 +194,13 @@ impl<T: Clone + Send + Sync> ModuleBuilder<T> {
 
     #[must_use]
     pub fn component(mut self, custom_id: &str, func: ComponentInteractionFunc<T>) -> Self {
+        assert!(
+            !custom_id.contains(custom_id::SEPARATOR),
+            "component custom_id '{}' can't contain '{}', it's used to separate arguments",
+            custom_id,
+            custom_id::SEPARATOR
+        );
+
         self.components.insert(
             custom_id.to_string(),
             ComponentInteractionHandler {
@@ -80,10 +212,25 @@ impl<T: Clone + Send + Sync> ModuleBuilder<T> {
         self
     }
 
+    #[must_use]
+    pub fn modal(mut self, custom_id: &str, func: ModalFunc<T>) -> Self {
+        self.modals.insert(
+            custom_id.to_string(),
+            ModalHandler {
+                module: self.name.clone(),
+                custom_id: custom_id.to_string(),
+                func,
+            },
+        );
+        self
+    }
+
     #[must_use]
     pub fn event(mut self, event: EventType, func: EventFunc<T>) -> Self {
-        self.events.entry(event).or_default().insert(EventHandler {
+        let handlers = self.events.entry(event).or_default();
+        handlers.insert(EventHandler {
             module: self.name.clone(),
+            id: handler_id(&self.name, event, handlers.len()),
             uuid: uuid::Uuid::now_v7().to_string(),
             event,
             func,
@@ -105,3 +252,20 @@ impl<T: Clone + Send + Sync> ModuleBuilder<T> {
         self
     }
 }
+
+fn has_subcommand(options: &[CommandOption], path: &[&str]) -> bool {
+    let Some((name, rest)) = path.split_first() else {
+        return false;
+    };
+
+    options.iter().any(|opt| {
+        opt.name == *name
+            && match opt.kind {
+                CommandOptionType::SubCommand => rest.is_empty(),
+                CommandOptionType::SubCommandGroup => {
+                    has_subcommand(opt.options.as_deref().unwrap_or_default(), rest)
+                }
+                _ => false,
+            }
+    })
+}
@@ -1,13 +1,24 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use twilight_gateway::EventType;
-use twilight_model::application::command::Command;
+use twilight_model::{
+    application::command::Command,
+    id::{
+        marker::{GuildMarker, UserMarker},
+        Id,
+    },
+};
 
 use super::Module;
 use crate::handler::{
-    command_handler::CommandHandler, component_interaction_handler::ComponentInteractionHandler,
-    event_handler::EventHandler, task_handler::TaskHandler,
+    autocomplete_handler::AutocompleteHandler, command_handler::CommandHandler,
+    component_interaction_handler::ComponentInteractionHandler, event_handler::EventHandler,
+    modal_handler::ModalHandler, task_handler::TaskHandler,
 };
+use crate::{custom_id, guild_modules::GuildModuleLookup, module_config::ConfigSchema, Error};
 
 #[derive(Clone)]
 #[expect(
@@ -16,9 +27,13 @@ use crate::handler::{
 )]
 pub struct Registry<T: Clone + Send + Sync> {
     modules: HashMap<String, Module<T>>,
+    guild_modules: Option<Arc<dyn GuildModuleLookup>>,
+    owners: HashSet<Id<UserMarker>>,
 
     pub(crate) commands: HashMap<String, CommandHandler<T>>,
+    pub(crate) autocompletes: HashMap<(String, String), AutocompleteHandler<T>>,
     pub(crate) components: HashMap<String, ComponentInteractionHandler<T>>,
+    pub(crate) modals: HashMap<String, ModalHandler<T>>,
     pub(crate) events: HashMap<EventType, HashSet<EventHandler<T>>>,
     pub tasks: HashMap<String, TaskHandler<T>>,
 }
@@ -27,8 +42,12 @@ impl<T: Clone + Send + Sync> Registry<T> {
     pub fn new() -> Self {
         Self {
             modules: HashMap::new(),
+            guild_modules: None,
+            owners: HashSet::new(),
             commands: HashMap::new(),
+            autocompletes: HashMap::new(),
             components: HashMap::new(),
+            modals: HashMap::new(),
             events: HashMap::new(),
             tasks: HashMap::new(),
         }
@@ -36,18 +55,74 @@ impl<T: Clone + Send + Sync> Registry<T> {
 
     pub fn register(&mut self, module: Module<T>) {
         self.commands.extend(module.commands.clone());
+        self.autocompletes.extend(module.autocompletes.clone());
         self.components.extend(module.components.clone());
+        self.modals.extend(module.modals.clone());
         self.events.extend(module.events.clone());
         self.tasks.extend(module.tasks.clone());
 
         self.modules.insert(module.name.clone(), module);
     }
 
+    pub fn set_guild_module_lookup(&mut self, lookup: impl GuildModuleLookup + 'static) {
+        self.guild_modules = Some(Arc::new(lookup));
+    }
+
+    pub fn set_owners(&mut self, owners: impl IntoIterator<Item = Id<UserMarker>>) {
+        self.owners = owners.into_iter().collect();
+    }
+
+    pub fn is_owner(&self, user_id: Id<UserMarker>) -> bool {
+        self.owners.contains(&user_id)
+    }
+
+    // whether a module is enabled for a guild, modules that aren't guild scoped
+    // or toggleable are always enabled, as is everything without a lookup
+    pub async fn module_enabled(
+        &self,
+        guild_id: Id<GuildMarker>,
+        module: &str,
+    ) -> Result<bool, Error> {
+        let Some(module) = self.modules.get(module) else {
+            return Err(format!("unknown module {}", module).into());
+        };
+        if !module.guild_scoped && !module.guild_toggleable {
+            return Ok(true);
+        }
+
+        let Some(lookup) = &self.guild_modules else {
+            return Ok(true);
+        };
+
+        Ok(lookup
+            .module_states(guild_id)
+            .await?
+            .get(&module.name)
+            .copied()
+            .unwrap_or(!module.guild_scoped))
+    }
+
+    pub fn invalidate_guild_modules(&self, guild_id: Id<GuildMarker>) {
+        if let Some(lookup) = &self.guild_modules {
+            lookup.invalidate(guild_id);
+        }
+    }
+
+    // event types that need to be received, interactions are always handled
+    // by the framework, other events only if a module has handlers for them
+    pub fn handled_events(&self) -> Vec<EventType> {
+        let mut events: Vec<EventType> = self.events.keys().copied().collect();
+        if !events.contains(&EventType::InteractionCreate) {
+            events.push(EventType::InteractionCreate);
+        }
+        events
+    }
+
     pub fn global_commands(&self) -> Vec<Command> {
         self.modules
             .values()
             .filter(|m| !m.guild_scoped) // filter out guild scoped modules
-            .flat_map(|m| m.commands.values().map(|ch| ch.definition.clone()))
+            .flat_map(|m| m.definitions.values().cloned())
             .collect()
     }
 
@@ -55,15 +130,52 @@ impl<T: Clone + Send + Sync> Registry<T> {
         Some(
             self.modules
                 .get(module)?
-                .commands
+                .definitions
                 .values()
-                .map(|ch| ch.definition.clone())
+                .cloned()
                 .collect(),
         )
     }
 
-    pub fn find_command(&self, name: &str) -> Option<&CommandHandler<T>> {
-        self.commands.get(name)
+    // `path` is the command name followed by any subcommand group and
+    // subcommand names, separated by spaces, e.g. "pk fronters update"
+    pub fn find_command(&self, path: &str) -> Option<&CommandHandler<T>> {
+        self.commands.get(path)
+    }
+
+    pub fn find_autocomplete(
+        &self,
+        command: &str,
+        option: &str,
+    ) -> Option<&AutocompleteHandler<T>> {
+        self.autocompletes
+            .get(&(command.to_string(), option.to_string()))
+    }
+
+    pub fn find_component(&self, custom_id: &str) -> Option<&ComponentInteractionHandler<T>> {
+        let (prefix, _) = custom_id::decode(custom_id);
+        self.components.get(prefix)
+    }
+
+    pub fn module_config(&self, module: &str) -> Option<&ConfigSchema> {
+        self.modules.get(module)?.config.as_ref()
+    }
+
+    pub fn configurable_module_names(&self) -> Vec<String> {
+        self.modules
+            .values()
+            .filter(|m| m.config.is_some())
+            .map(|m| m.name.clone())
+            .collect()
+    }
+
+    // modules guilds can enable/disable, includes toggleable global modules
+    pub fn toggleable_module_names(&self) -> Vec<String> {
+        self.modules
+            .values()
+            .filter(|m| m.guild_scoped || m.guild_toggleable)
+            .map(|m| m.name.clone())
+            .collect()
     }
 
     pub fn guild_module_names(&self) -> Vec<String> {
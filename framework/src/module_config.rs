@@ -0,0 +1,207 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingKind {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Channel,
+    Role,
+}
+
+#[derive(Clone, Debug)]
+pub struct Setting {
+    pub key: &'static str,
+    pub description: &'static str,
+    pub kind: SettingKind,
+}
+
+// typed per-guild settings for a module, stored as a json object, settings
+// that haven't been set use the value from `Default` so implementors should
+// use `#[serde(default)]`
+pub trait ModuleConfig: Serialize + DeserializeOwned + Default {
+    // the settings that can be changed with /config, keys need to match the
+    // serialized field names
+    fn settings() -> Vec<Setting>;
+}
+
+#[derive(Clone, Debug)]
+pub struct ConfigSchema {
+    pub settings: Vec<Setting>,
+    pub defaults: Value,
+
+    validate: fn(&Value) -> Result<(), Error>,
+}
+
+impl ConfigSchema {
+    pub fn new<C: ModuleConfig>() -> Self {
+        Self {
+            settings: C::settings(),
+            defaults: serde_json::to_value(C::default())
+                .expect("couldn't serialize default module config"),
+            validate: validate::<C>,
+        }
+    }
+
+    pub fn setting(&self, key: &str) -> Option<&Setting> {
+        self.settings.iter().find(|setting| setting.key == key)
+    }
+
+    // checks whether the settings can be deserialized into the module's config
+    pub fn validate(&self, settings: &Value) -> Result<(), Error> {
+        (self.validate)(settings)
+    }
+
+    // parses user input for a setting into the value that gets stored
+    pub fn parse_value(&self, key: &str, input: &str) -> Result<Value, Error> {
+        let setting = self
+            .setting(key)
+            .ok_or_else(|| format!("unknown setting {}", key))?;
+        let input = input.trim();
+
+        Ok(match setting.kind {
+            SettingKind::String => Value::String(input.to_string()),
+            SettingKind::Integer => Value::from(
+                input
+                    .parse::<i64>()
+                    .map_err(|_| format!("'{}' isn't a whole number", input))?,
+            ),
+            SettingKind::Number => serde_json::Number::from_f64(
+                input
+                    .parse::<f64>()
+                    .map_err(|_| format!("'{}' isn't a number", input))?,
+            )
+            .map(Value::Number)
+            .ok_or_else(|| format!("'{}' isn't a number", input))?,
+            SettingKind::Boolean => Value::Bool(parse_bool(input)?),
+            SettingKind::Channel => Value::String(parse_mention(input, "<#")?),
+            SettingKind::Role => Value::String(parse_mention(input, "<@&")?),
+        })
+    }
+}
+
+// deserializes stored settings into a module's config
+pub fn parse<C: ModuleConfig>(settings: Value) -> Result<C, Error> {
+    Ok(serde_json::from_value(settings)?)
+}
+
+fn validate<C: ModuleConfig>(settings: &Value) -> Result<(), Error> {
+    parse::<C>(settings.clone()).map(|_| ())
+}
+
+fn parse_bool(input: &str) -> Result<bool, Error> {
+    match input.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(format!("'{}' isn't true or false", input).into()),
+    }
+}
+
+// accepts both mentions (e.g. `<#123>`) and raw ids, ids are stored as strings
+// same as twilight serializes them
+fn parse_mention(input: &str, prefix: &str) -> Result<String, Error> {
+    let id = input
+        .strip_prefix(prefix)
+        .and_then(|id| id.strip_suffix('>'))
+        .unwrap_or(input);
+
+    match id.parse::<u64>() {
+        Ok(id) if id != 0 => Ok(id.to_string()),
+        _ => Err(format!("'{}' isn't a valid mention or id", input).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(default)]
+    struct TestConfig {
+        enabled: bool,
+        limit: i64,
+        channel: Option<String>,
+    }
+
+    impl Default for TestConfig {
+        fn default() -> Self {
+            Self {
+                enabled: true,
+                limit: 10,
+                channel: None,
+            }
+        }
+    }
+
+    impl ModuleConfig for TestConfig {
+        fn settings() -> Vec<Setting> {
+            vec![
+                Setting {
+                    key: "enabled",
+                    description: "whether it's enabled",
+                    kind: SettingKind::Boolean,
+                },
+                Setting {
+                    key: "limit",
+                    description: "the limit",
+                    kind: SettingKind::Integer,
+                },
+                Setting {
+                    key: "channel",
+                    description: "the channel",
+                    kind: SettingKind::Channel,
+                },
+            ]
+        }
+    }
+
+    #[test]
+    fn parse_value_test() {
+        let schema = ConfigSchema::new::<TestConfig>();
+
+        assert_eq!(
+            schema.parse_value("enabled", "off").unwrap(),
+            Value::Bool(false)
+        );
+        assert_eq!(schema.parse_value("limit", " 5 ").unwrap(), Value::from(5));
+        assert_eq!(
+            schema.parse_value("channel", "<#1234>").unwrap(),
+            Value::String(String::from("1234"))
+        );
+        assert_eq!(
+            schema.parse_value("channel", "1234").unwrap(),
+            Value::String(String::from("1234"))
+        );
+        assert!(
+            schema.parse_value("limit", "five").is_err(),
+            "non-numeric input should be rejected"
+        );
+        assert!(
+            schema.parse_value("unknown", "1").is_err(),
+            "unknown settings should be rejected"
+        );
+    }
+
+    #[test]
+    fn defaults_test() {
+        let schema = ConfigSchema::new::<TestConfig>();
+        assert_eq!(schema.defaults.get("limit"), Some(&Value::from(10)));
+
+        let config = parse::<TestConfig>(serde_json::json!({ "limit": 3 })).unwrap();
+        assert!(config.enabled, "missing settings should use their default");
+        assert_eq!(config.limit, 3);
+
+        assert!(
+            schema
+                .validate(&serde_json::json!({ "limit": "three" }))
+                .is_err(),
+            "settings of the wrong type should fail validation"
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandDataOption, CommandOptionValue},
+        InteractionChannel, InteractionDataResolved,
+    },
+    channel::Attachment,
+    guild::Role,
+    user::User,
+};
+
+use crate::{context::command_context::subcommand_options, Error};
+
+// a value that can be extracted from a command option, user, channel, role,
+// mentionable and attachment options only contain an id, so these get looked
+// up in the command's resolved data
+pub trait FromOption: Sized {
+    // name of the option type, used in error messages
+    const KIND: &'static str;
+
+    fn from_option(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, Error>;
+}
+
+#[derive(Clone, Debug)]
+pub enum Mentionable {
+    User(User),
+    Role(Role),
+}
+
+// parses all options of a command into a struct, usually implemented using
+// the `command_options!` macro
+pub trait CommandOptions: Sized {
+    fn from_options(options: &OptionResolver<'_>) -> Result<Self, Error>;
+}
+
+// a field of a `CommandOptions` struct, fields of type `Option<T>` are
+// optional, all others are required
+pub trait OptionField: Sized {
+    fn from_resolver(options: &OptionResolver<'_>, name: &str) -> Result<Self, Error>;
+}
+
+impl<V: FromOption> OptionField for V {
+    fn from_resolver(options: &OptionResolver<'_>, name: &str) -> Result<Self, Error> {
+        options.get(name)
+    }
+}
+
+impl<V: FromOption> OptionField for Option<V> {
+    fn from_resolver(options: &OptionResolver<'_>, name: &str) -> Result<Self, Error> {
+        options.get_optional(name)
+    }
+}
+
+pub struct OptionResolver<'a> {
+    options: &'a [CommandDataOption],
+    resolved: Option<&'a InteractionDataResolved>,
+}
+
+impl<'a> OptionResolver<'a> {
+    pub fn new(command: &'a CommandData) -> Self {
+        Self {
+            options: subcommand_options(&command.options),
+            resolved: command.resolved.as_ref(),
+        }
+    }
+
+    pub fn get_optional<V: FromOption>(&self, name: &str) -> Result<Option<V>, Error> {
+        let Some(opt) = self.options.iter().find(|opt| opt.name == name) else {
+            return Ok(None);
+        };
+
+        V::from_option(&opt.value, self.resolved)
+            .map(Some)
+            .map_err(|err| format!("invalid option '{}': {}", name, err).into())
+    }
+
+    pub fn get<V: FromOption>(&self, name: &str) -> Result<V, Error> {
+        self.get_optional(name)?
+            .ok_or_else(|| format!("couldn't find command argument {}", name).into())
+    }
+
+    pub fn parse<O: CommandOptions>(&self) -> Result<O, Error> {
+        O::from_options(self)
+    }
+}
+
+fn wrong_kind<V: FromOption>(value: &CommandOptionValue) -> Error {
+    format!("not a {} option, got {}", V::KIND, value.kind().kind()).into()
+}
+
+fn resolved_data(
+    resolved: Option<&InteractionDataResolved>,
+) -> Result<&InteractionDataResolved, Error> {
+    resolved.ok_or_else(|| "command has no resolved data".into())
+}
+
+impl FromOption for String {
+    const KIND: &'static str = "string";
+
+    fn from_option(
+        value: &CommandOptionValue,
+        _resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, Error> {
+        match value {
+            CommandOptionValue::String(value) => Ok(value.clone()),
+            _ => Err(wrong_kind::<Self>(value)),
+        }
+    }
+}
+
+impl FromOption for i64 {
+    const KIND: &'static str = "integer";
+
+    fn from_option(
+        value: &CommandOptionValue,
+        _resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, Error> {
+        match value {
+            CommandOptionValue::Integer(value) => Ok(*value),
+            _ => Err(wrong_kind::<Self>(value)),
+        }
+    }
+}
+
+impl FromOption for f64 {
+    const KIND: &'static str = "number";
+
+    fn from_option(
+        value: &CommandOptionValue,
+        _resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, Error> {
+        match value {
+            CommandOptionValue::Number(value) => Ok(*value),
+            _ => Err(wrong_kind::<Self>(value)),
+        }
+    }
+}
+
+impl FromOption for bool {
+    const KIND: &'static str = "boolean";
+
+    fn from_option(
+        value: &CommandOptionValue,
+        _resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, Error> {
+        match value {
+            CommandOptionValue::Boolean(value) => Ok(*value),
+            _ => Err(wrong_kind::<Self>(value)),
+        }
+    }
+}
+
+impl FromOption for User {
+    const KIND: &'static str = "user";
+
+    fn from_option(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, Error> {
+        let CommandOptionValue::User(id) = value else {
+            return Err(wrong_kind::<Self>(value));
+        };
+
+        resolved_data(resolved)?
+            .users
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("couldn't resolve user {}", id).into())
+    }
+}
+
+impl FromOption for InteractionChannel {
+    const KIND: &'static str = "channel";
+
+    fn from_option(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, Error> {
+        let CommandOptionValue::Channel(id) = value else {
+            return Err(wrong_kind::<Self>(value));
+        };
+
+        resolved_data(resolved)?
+            .channels
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("couldn't resolve channel {}", id).into())
+    }
+}
+
+impl FromOption for Role {
+    const KIND: &'static str = "role";
+
+    fn from_option(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, Error> {
+        let CommandOptionValue::Role(id) = value else {
+            return Err(wrong_kind::<Self>(value));
+        };
+
+        resolved_data(resolved)?
+            .roles
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("couldn't resolve role {}", id).into())
+    }
+}
+
+impl FromOption for Mentionable {
+    const KIND: &'static str = "mentionable";
+
+    fn from_option(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, Error> {
+        let CommandOptionValue::Mentionable(id) = value else {
+            return Err(wrong_kind::<Self>(value));
+        };
+        let resolved = resolved_data(resolved)?;
+
+        if let Some(user) = resolved.users.get(&id.cast()) {
+            return Ok(Self::User(user.clone()));
+        }
+
+        resolved
+            .roles
+            .get(&id.cast())
+            .map(|role| Self::Role(role.clone()))
+            .ok_or_else(|| format!("couldn't resolve user or role {}", id).into())
+    }
+}
+
+impl FromOption for Attachment {
+    const KIND: &'static str = "attachment";
+
+    fn from_option(
+        value: &CommandOptionValue,
+        resolved: Option<&InteractionDataResolved>,
+    ) -> Result<Self, Error> {
+        let CommandOptionValue::Attachment(id) = value else {
+            return Err(wrong_kind::<Self>(value));
+        };
+
+        resolved_data(resolved)?
+            .attachments
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("couldn't resolve attachment {}", id).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{application::command::CommandType, id::Id};
+
+    use super::*;
+
+    crate::command_options! {
+        struct TestOptions {
+            name: String,
+            count: Option<i64>,
+            enabled: Option<bool>,
+        }
+    }
+
+    fn command_data(options: Vec<CommandDataOption>) -> CommandData {
+        CommandData {
+            guild_id: None,
+            id: Id::new(1),
+            name: String::from("test"),
+            kind: CommandType::ChatInput,
+            options,
+            resolved: None,
+            target_id: None,
+        }
+    }
+
+    fn option(name: &str, value: CommandOptionValue) -> CommandDataOption {
+        CommandDataOption {
+            name: name.into(),
+            value,
+        }
+    }
+
+    #[test]
+    fn option_resolver_test() {
+        let data = command_data(vec![option(
+            "sub",
+            CommandOptionValue::SubCommand(vec![
+                option("name", CommandOptionValue::String(String::from("foo"))),
+                option("count", CommandOptionValue::Integer(3)),
+            ]),
+        )]);
+        let options = OptionResolver::new(&data);
+
+        assert_eq!(options.get::<String>("name").unwrap(), "foo");
+        assert_eq!(options.get_optional::<i64>("count").unwrap(), Some(3));
+        assert_eq!(options.get_optional::<bool>("enabled").unwrap(), None);
+        assert!(
+            options.get::<bool>("enabled").is_err(),
+            "missing required options should error"
+        );
+        assert!(
+            options.get::<i64>("name").is_err(),
+            "options of the wrong type should error"
+        );
+        assert!(
+            options.get::<User>("name").is_err(),
+            "options of the wrong type should error"
+        );
+    }
+
+    #[test]
+    fn command_options_test() {
+        let data = command_data(vec![
+            option("name", CommandOptionValue::String(String::from("foo"))),
+            option("enabled", CommandOptionValue::Boolean(true)),
+        ]);
+
+        let parsed = OptionResolver::new(&data).parse::<TestOptions>().unwrap();
+        assert_eq!(parsed.name, "foo");
+        assert_eq!(parsed.count, None);
+        assert_eq!(parsed.enabled, Some(true));
+
+        let data = command_data(vec![]);
+        assert!(
+            OptionResolver::new(&data).parse::<TestOptions>().is_err(),
+            "missing required fields should error"
+        );
+    }
+}
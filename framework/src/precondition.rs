@@ -0,0 +1,131 @@
+use twilight_model::{gateway::payload::incoming::InteractionCreate, guild::Permissions};
+
+use crate::Registry;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Precondition {
+    // can only be used inside of a guild
+    GuildOnly,
+    // the invoking member needs all of these permissions
+    MemberPermissions(Permissions),
+    // the bot needs all of these permissions
+    BotPermissions(Permissions),
+    // the command's module needs to be enabled for the guild, always checked
+    // for guild scoped and toggleable modules when used inside a guild
+    ModuleEnabled,
+    // can only be used by the owner(s) of the bot application
+    OwnerOnly,
+}
+
+impl Precondition {
+    // returns a user facing reason when the precondition isn't met
+    pub(crate) async fn check<T: Clone + Send + Sync>(
+        &self,
+        module: &str,
+        event: &InteractionCreate,
+        registry: &Registry<T>,
+    ) -> Result<(), String> {
+        match self {
+            Self::GuildOnly => {
+                if event.guild_id.is_none() {
+                    return Err("this command can only be used in a server".into());
+                }
+            }
+            Self::MemberPermissions(required) => {
+                let permissions = event
+                    .member
+                    .as_ref()
+                    .and_then(|member| member.permissions)
+                    .unwrap_or_else(Permissions::empty);
+
+                if let Some(missing) = missing_permissions(*required, permissions) {
+                    return Err(format!("you're missing permissions: {}", missing));
+                }
+            }
+            Self::BotPermissions(required) => {
+                let permissions = event.app_permissions.unwrap_or_else(Permissions::empty);
+
+                if let Some(missing) = missing_permissions(*required, permissions) {
+                    return Err(format!("I'm missing permissions: {}", missing));
+                }
+            }
+            Self::ModuleEnabled => {
+                let Some(guild_id) = event.guild_id else {
+                    return Err("this command can only be used in a server".into());
+                };
+
+                match registry.module_enabled(guild_id, module).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        return Err(format!(
+                            "the {} module isn't enabled in this server",
+                            module
+                        ))
+                    }
+                    Err(err) => {
+                        tracing::warn!(
+                            ?err,
+                            guild_id = guild_id.get(),
+                            module,
+                            "error looking up guild modules"
+                        );
+                        return Err("couldn't check whether this module is enabled".into());
+                    }
+                }
+            }
+            Self::OwnerOnly => {
+                if !event
+                    .author_id()
+                    .is_some_and(|user_id| registry.is_owner(user_id))
+                {
+                    return Err("this command can only be used by the bot owner".into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn missing_permissions(required: Permissions, permissions: Permissions) -> Option<String> {
+    if permissions.contains(Permissions::ADMINISTRATOR) {
+        return None;
+    }
+
+    let missing = required.difference(permissions);
+    if missing.is_empty() {
+        return None;
+    }
+
+    Some(
+        missing
+            .iter_names()
+            .map(|(name, _)| name)
+            .collect::<Vec<&str>>()
+            .join(", "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_permissions_test() {
+        assert_eq!(
+            missing_permissions(Permissions::MANAGE_GUILD, Permissions::MANAGE_GUILD),
+            None
+        );
+        assert_eq!(
+            missing_permissions(Permissions::MANAGE_GUILD, Permissions::ADMINISTRATOR),
+            None
+        );
+        assert_eq!(
+            missing_permissions(
+                Permissions::MANAGE_GUILD | Permissions::MANAGE_ROLES,
+                Permissions::MANAGE_GUILD
+            ),
+            Some(String::from("MANAGE_ROLES"))
+        );
+    }
+}
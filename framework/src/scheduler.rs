@@ -1,7 +1,8 @@
-use std::{collections::HashMap, future::Future, pin::Pin};
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
 
 use async_cron_scheduler::{Job, JobId, Scheduler as CronScheduler};
 use chrono::Utc;
+use tokio::sync::watch;
 
 use crate::{
     context::{Context, TaskContext},
@@ -12,6 +13,24 @@ pub struct Scheduler {
     job_map: HashMap<String, JobId>,
     scheduler: CronScheduler<Utc>,
     runner: Option<Pin<Box<dyn Future<Output = ()> + Send + Sync>>>,
+    // how many tasks are running, so we can wait for them when shutting down
+    running: Arc<watch::Sender<usize>>,
+}
+
+// counts a task as running until it's dropped, even if the task panics
+struct RunningTask(Arc<watch::Sender<usize>>);
+
+impl RunningTask {
+    fn start(running: &Arc<watch::Sender<usize>>) -> Self {
+        running.send_modify(|count| *count += 1);
+        Self(Arc::clone(running))
+    }
+}
+
+impl Drop for RunningTask {
+    fn drop(&mut self) {
+        self.0.send_modify(|count| *count -= 1);
+    }
 }
 
 impl Scheduler {
@@ -26,6 +45,7 @@ impl Scheduler {
             job_map: HashMap::new(),
             scheduler,
             runner: Some(Box::pin(service)),
+            running: Arc::new(watch::Sender::new(0)),
         }
     }
 
@@ -36,13 +56,16 @@ impl Scheduler {
     ) {
         let job = Job::<Utc>::cron_schedule(task.cron.clone());
         let job_name = task.name.clone();
+        let running = Arc::clone(&self.running);
         let job_id = self
             .scheduler
             .insert(job, move |_id| {
                 let job_ctx = ctx.clone();
                 let job_handler = task.clone();
+                let running_task = RunningTask::start(&running);
 
                 tokio::spawn(async move {
+                    let _running_task = running_task;
                     if let Err(err) = job_handler.run(TaskContext::from_context(job_ctx)).await {
                         tracing::error!("error running task {}: {}", job_handler.name, err);
                     };
@@ -76,4 +99,11 @@ impl Scheduler {
 
         tokio::spawn(self.runner.take().unwrap())
     }
+
+    // waits until no tasks are running, stop the scheduler first so no new
+    // ones get started
+    pub async fn wait_for_tasks(&self) {
+        // can't fail as we hold on to the sender
+        let _ = self.running.subscribe().wait_for(|count| *count == 0).await;
+    }
 }
@@ -3,13 +3,26 @@ compile_error!(
     "can only pick one amqp implementation, `amqp-amqprs` and `amqp-lapin` are mutually exclusive"
 );
 
-#[cfg(feature = "amqp-amqprs")]
-mod amqprs;
-#[cfg(feature = "amqp-lapin")]
-mod lapin;
+use tulpje_shared::{
+    amqp::{BufferedPublisher, ExchangeKind, Topology},
+    routing,
+};
 
 #[cfg(feature = "amqp-amqprs")]
-pub(crate) use amqprs::create;
+type Backend = tulpje_shared::amqp::amqprs::AmqprsConnection;
 
 #[cfg(feature = "amqp-lapin")]
-pub(crate) use lapin::create;
+type Backend = tulpje_shared::amqp::lapin::LapinConnection;
+
+// how many events are kept while rabbitmq is unreachable, events received while
+// the buffer is full get dropped
+const BUFFER_SIZE: usize = 10_000;
+
+// events are queued and published in the background, so the shard can keep
+// heartbeating while rabbitmq is down
+pub(crate) fn create(addr: &str) -> BufferedPublisher {
+    // declare the exchange, handlers declare and bind their own queue
+    let topology = Topology::new().exchange(routing::EXCHANGE, ExchangeKind::Topic);
+
+    BufferedPublisher::new::<Backend>(addr, topology, routing::EXCHANGE, BUFFER_SIZE)
+}
@@ -0,0 +1,468 @@
+use bb8_redis::{
+    redis::{self, AsyncCommands as _},
+    RedisConnectionManager,
+};
+use serde::Serialize;
+use twilight_gateway::{Event, Intents};
+use twilight_model::{
+    channel::{Channel, Message},
+    gateway::payload::incoming::{GuildCreate, MemberUpdate, MessageUpdate},
+    guild::{Emoji, Guild, Member, PartialGuild},
+    id::{marker::GuildMarker, Id},
+};
+
+use tulpje_shared::cache;
+
+// keeps the discord cache in redis up-to-date, see `tulpje_shared::cache`
+#[derive(Clone)]
+pub struct DiscordCache {
+    pub redis: bb8::Pool<RedisConnectionManager>,
+    // emojis and members are only cached when we receive updates for them,
+    // otherwise the cache would go stale and handlers should use the http api
+    // instead
+    pub emojis: bool,
+    pub members: bool,
+}
+
+impl DiscordCache {
+    pub fn new(redis: bb8::Pool<RedisConnectionManager>, intents: Intents) -> Self {
+        Self {
+            redis,
+            emojis: intents.contains(Intents::GUILD_EMOJIS_AND_STICKERS),
+            members: intents.contains(Intents::GUILD_MEMBERS),
+        }
+    }
+
+    // lets handlers know the cache is being kept up-to-date, runs forever
+    pub async fn heartbeat(self) {
+        loop {
+            let result = async {
+                self.redis
+                    .get()
+                    .await?
+                    .set_ex::<&str, bool, ()>(cache::HEARTBEAT_KEY, true, cache::HEARTBEAT_TTL)
+                    .await?;
+
+                Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+            };
+            if let Err(err) = result.await {
+                tracing::error!("error updating cache heartbeat: {}", err);
+            }
+
+            tokio::time::sleep(std::time::Duration::from_secs(cache::HEARTBEAT_TTL / 3)).await;
+        }
+    }
+
+    pub async fn handle_event(&self, event: &Event) -> Result<(), Box<dyn std::error::Error>> {
+        match event {
+            Event::GuildCreate(created) => match created.as_ref() {
+                GuildCreate::Available(guild) => self.guild_created(guild).await,
+                GuildCreate::Unavailable(_) => Ok(()),
+            },
+            Event::GuildUpdate(updated) => self.guild_updated(&updated.0).await,
+            Event::GuildDelete(deleted) => self.guild_deleted(deleted.id).await,
+            Event::ChannelCreate(created) => self.channel_updated(&created.0).await,
+            Event::ChannelUpdate(updated) => self.channel_updated(&updated.0).await,
+            Event::ChannelDelete(deleted) => self.channel_deleted(&deleted.0).await,
+            Event::RoleCreate(created) => {
+                self.hash_set(
+                    cache::roles_key(created.guild_id),
+                    created.role.id,
+                    &created.role,
+                )
+                .await
+            }
+            Event::RoleUpdate(updated) => {
+                self.hash_set(
+                    cache::roles_key(updated.guild_id),
+                    updated.role.id,
+                    &updated.role,
+                )
+                .await
+            }
+            Event::RoleDelete(deleted) => {
+                self.hash_delete(cache::roles_key(deleted.guild_id), deleted.role_id)
+                    .await
+            }
+            Event::GuildEmojisUpdate(_) if !self.emojis => Ok(()),
+            Event::GuildEmojisUpdate(updated) => {
+                let emojis = serialize_by_id(&updated.emojis, |emoji| emoji.id)?;
+                self.hash_replace(cache::emojis_key(updated.guild_id), &emojis)
+                    .await
+            }
+            Event::MemberAdd(_) | Event::MemberUpdate(_) | Event::MemberRemove(_)
+                if !self.members =>
+            {
+                Ok(())
+            }
+            Event::MemberAdd(added) => {
+                self.hash_set(
+                    cache::members_key(added.guild_id),
+                    added.member.user.id,
+                    &added.member,
+                )
+                .await
+            }
+            Event::MemberUpdate(updated) => self.member_updated(updated).await,
+            Event::MemberRemove(removed) => {
+                self.hash_delete(cache::members_key(removed.guild_id), removed.user.id)
+                    .await
+            }
+            Event::MessageCreate(created) => self.message_created(&created.0).await,
+            Event::MessageUpdate(updated) => self.message_updated(updated).await,
+            Event::MessageDelete(deleted) => {
+                self.delete(vec![cache::message_key(deleted.id)]).await
+            }
+            Event::MessageDeleteBulk(deleted) => {
+                self.delete(
+                    deleted
+                        .ids
+                        .iter()
+                        .copied()
+                        .map(cache::message_key)
+                        .collect(),
+                )
+                .await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn guild_created(&self, guild: &Guild) -> Result<(), Box<dyn std::error::Error>> {
+        let channels = serialize_by_id(&guild.channels, |channel| channel.id)?;
+        let roles = serialize_by_id(&guild.roles, |role| role.id)?;
+        let emojis = self.serialize_emojis(&guild.emojis)?;
+        let members = self.serialize_members(&guild.members)?;
+
+        // everything that has its own hash, or isn't kept up-to-date, is
+        // stripped from the guild itself
+        let stripped = Guild {
+            channels: Vec::new(),
+            emojis: Vec::new(),
+            members: Vec::new(),
+            presences: Vec::new(),
+            roles: Vec::new(),
+            stage_instances: Vec::new(),
+            threads: Vec::new(),
+            voice_states: Vec::new(),
+            ..guild.clone()
+        };
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .set(
+                cache::guild_key(guild.id),
+                serde_json::to_string(&stripped)?,
+            )
+            .ignore();
+        for (key, items) in [
+            (cache::channels_key(guild.id), channels),
+            (cache::roles_key(guild.id), roles),
+            (cache::emojis_key(guild.id), emojis),
+            (cache::members_key(guild.id), members),
+        ] {
+            pipe.del(&key).ignore();
+            if !items.is_empty() {
+                pipe.hset_multiple(&key, &items).ignore();
+            }
+        }
+
+        // an empty emoji hash doesn't exist, so mark whether it's kept
+        if self.emojis {
+            pipe.set(cache::emojis_cached_key(guild.id), true).ignore();
+        } else {
+            pipe.del(cache::emojis_cached_key(guild.id)).ignore();
+        }
+
+        pipe.query_async::<()>(&mut *self.redis.get().await?)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn guild_updated(
+        &self,
+        partial: &PartialGuild,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut redis = self.redis.get().await?;
+
+        // can't construct a full guild from an update, so only update it if
+        // it's already cached
+        let Some(json_guild) = redis
+            .get::<String, Option<String>>(cache::guild_key(partial.id))
+            .await?
+        else {
+            return Ok(());
+        };
+        let guild = merge_partial_guild(serde_json::from_str(&json_guild)?, partial.clone());
+
+        let roles = serialize_by_id(&partial.roles, |role| role.id)?;
+        let emojis = self.serialize_emojis(&partial.emojis)?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .set(cache::guild_key(guild.id), serde_json::to_string(&guild)?)
+            .ignore();
+        for (key, items) in [
+            (cache::roles_key(guild.id), roles),
+            (cache::emojis_key(guild.id), emojis),
+        ] {
+            pipe.del(&key).ignore();
+            if !items.is_empty() {
+                pipe.hset_multiple(&key, &items).ignore();
+            }
+        }
+
+        pipe.query_async::<()>(&mut *redis)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn guild_deleted(
+        &self,
+        guild_id: Id<GuildMarker>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.delete(vec![
+            cache::guild_key(guild_id),
+            cache::channels_key(guild_id),
+            cache::roles_key(guild_id),
+            cache::emojis_key(guild_id),
+            cache::emojis_cached_key(guild_id),
+            cache::members_key(guild_id),
+        ])
+        .await
+    }
+
+    async fn channel_updated(&self, channel: &Channel) -> Result<(), Box<dyn std::error::Error>> {
+        // we don't cache dm channels
+        let Some(guild_id) = channel.guild_id else {
+            return Ok(());
+        };
+
+        self.hash_set(cache::channels_key(guild_id), channel.id, channel)
+            .await
+    }
+
+    async fn channel_deleted(&self, channel: &Channel) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(guild_id) = channel.guild_id else {
+            return Ok(());
+        };
+
+        self.hash_delete(cache::channels_key(guild_id), channel.id)
+            .await
+    }
+
+    async fn member_updated(
+        &self,
+        updated: &MemberUpdate,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut redis = self.redis.get().await?;
+        let key = cache::members_key(updated.guild_id);
+
+        // updates don't contain every field, so only update cached members
+        let Some(json_member) = redis
+            .hget::<&str, String, Option<String>>(&key, updated.user.id.to_string())
+            .await?
+        else {
+            return Ok(());
+        };
+        let cached: Member = serde_json::from_str(&json_member)?;
+        let member = Member {
+            avatar: updated.avatar,
+            communication_disabled_until: updated.communication_disabled_until,
+            deaf: updated.deaf.unwrap_or(cached.deaf),
+            flags: updated.flags.unwrap_or(cached.flags),
+            joined_at: updated.joined_at,
+            mute: updated.mute.unwrap_or(cached.mute),
+            nick: updated.nick.clone(),
+            pending: updated.pending,
+            premium_since: updated.premium_since,
+            roles: updated.roles.clone(),
+            user: updated.user.clone(),
+        };
+
+        redis
+            .hset::<&str, String, String, ()>(
+                &key,
+                updated.user.id.to_string(),
+                serde_json::to_string(&member)?,
+            )
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn message_created(&self, message: &Message) -> Result<(), Box<dyn std::error::Error>> {
+        self.redis
+            .get()
+            .await?
+            .set_ex::<String, String, ()>(
+                cache::message_key(message.id),
+                serde_json::to_string(message)?,
+                cache::MESSAGE_TTL,
+            )
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn message_updated(
+        &self,
+        updated: &MessageUpdate,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut redis = self.redis.get().await?;
+        let key = cache::message_key(updated.id);
+
+        // updates only contain the changed fields, so only update cached messages
+        let Some(json_message) = redis.get::<&str, Option<String>>(&key).await? else {
+            return Ok(());
+        };
+        let cached: Message = serde_json::from_str(&json_message)?;
+        let message = Message {
+            attachments: updated.attachments.clone().unwrap_or(cached.attachments),
+            content: updated.content.clone().unwrap_or(cached.content),
+            edited_timestamp: updated.edited_timestamp.or(cached.edited_timestamp),
+            embeds: updated.embeds.clone().unwrap_or(cached.embeds),
+            mention_everyone: updated.mention_everyone.unwrap_or(cached.mention_everyone),
+            mention_roles: updated
+                .mention_roles
+                .clone()
+                .unwrap_or(cached.mention_roles),
+            mentions: updated.mentions.clone().unwrap_or(cached.mentions),
+            pinned: updated.pinned.unwrap_or(cached.pinned),
+            ..cached
+        };
+
+        redis
+            .set_ex::<&str, String, ()>(&key, serde_json::to_string(&message)?, cache::MESSAGE_TTL)
+            .await
+            .map_err(Into::into)
+    }
+
+    // nothing is cached when we don't receive emoji updates, so the emoji hash
+    // gets removed and handlers fall back to the http api
+    fn serialize_emojis(
+        &self,
+        emojis: &[Emoji],
+    ) -> Result<Vec<(String, String)>, serde_json::Error> {
+        if !self.emojis {
+            return Ok(Vec::new());
+        }
+
+        serialize_by_id(emojis, |emoji| emoji.id)
+    }
+
+    // same as emojis, without member updates the member hash gets removed
+    fn serialize_members(
+        &self,
+        members: &[Member],
+    ) -> Result<Vec<(String, String)>, serde_json::Error> {
+        if !self.members {
+            return Ok(Vec::new());
+        }
+
+        serialize_by_id(members, |member| member.user.id)
+    }
+
+    async fn hash_set<I: ToString, V: Serialize>(
+        &self,
+        key: String,
+        id: I,
+        value: &V,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.redis
+            .get()
+            .await?
+            .hset::<String, String, String, ()>(key, id.to_string(), serde_json::to_string(value)?)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn hash_delete<I: ToString>(
+        &self,
+        key: String,
+        id: I,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.redis
+            .get()
+            .await?
+            .hdel::<String, String, ()>(key, id.to_string())
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn hash_replace(
+        &self,
+        key: String,
+        items: &[(String, String)],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(&key).ignore();
+        if !items.is_empty() {
+            pipe.hset_multiple(&key, items).ignore();
+        }
+
+        pipe.query_async::<()>(&mut *self.redis.get().await?)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn delete(&self, keys: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        self.redis
+            .get()
+            .await?
+            .del::<Vec<String>, ()>(keys)
+            .await
+            .map_err(Into::into)
+    }
+}
+
+// hash fields and values for a list of entities
+fn serialize_by_id<V: Serialize, I: ToString>(
+    values: &[V],
+    id: impl Fn(&V) -> I,
+) -> Result<Vec<(String, String)>, serde_json::Error> {
+    values
+        .iter()
+        .map(|value| Ok((id(value).to_string(), serde_json::to_string(value)?)))
+        .collect()
+}
+
+fn merge_partial_guild(guild: Guild, partial: PartialGuild) -> Guild {
+    Guild {
+        afk_channel_id: partial.afk_channel_id,
+        afk_timeout: partial.afk_timeout,
+        application_id: partial.application_id,
+        banner: partial.banner,
+        default_message_notifications: partial.default_message_notifications,
+        description: partial.description,
+        discovery_splash: partial.discovery_splash,
+        explicit_content_filter: partial.explicit_content_filter,
+        features: partial.features,
+        icon: partial.icon,
+        max_members: partial.max_members,
+        max_presences: partial.max_presences,
+        member_count: partial.member_count.or(guild.member_count),
+        mfa_level: partial.mfa_level,
+        name: partial.name,
+        nsfw_level: partial.nsfw_level,
+        owner_id: partial.owner_id,
+        owner: partial.owner,
+        permissions: partial.permissions,
+        preferred_locale: partial.preferred_locale,
+        premium_progress_bar_enabled: partial.premium_progress_bar_enabled,
+        premium_subscription_count: partial.premium_subscription_count,
+        premium_tier: partial.premium_tier,
+        public_updates_channel_id: partial.public_updates_channel_id,
+        rules_channel_id: partial.rules_channel_id,
+        splash: partial.splash,
+        system_channel_flags: partial.system_channel_flags,
+        system_channel_id: partial.system_channel_id,
+        verification_level: partial.verification_level,
+        vanity_url_code: partial.vanity_url_code,
+        widget_channel_id: partial.widget_channel_id,
+        widget_enabled: partial.widget_enabled,
+        ..guild
+    }
+}
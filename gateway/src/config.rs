@@ -1,19 +1,189 @@
 use serde::{Deserialize, Serialize};
 use serde_envfile::Error;
+use twilight_gateway::{EventTypeFlags, Intents};
+use twilight_model::gateway::event::EventType;
+
+// no privileged intents besides message content, which emoji tracking needs,
+// emoji tracking also needs emoji updates to know which emojis a guild has
+const DEFAULT_INTENTS: &str =
+    "GUILDS,GUILD_EXPRESSIONS,GUILD_MESSAGES,GUILD_MESSAGE_REACTIONS,MESSAGE_CONTENT";
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub discord_token: String,
     pub discord_proxy: String,
     pub discord_gateway_queue: String,
+    // ignored when following the shard plan
+    #[serde(default)]
     pub shard_id: u32,
+    #[serde(default = "default_shard_count")]
     pub shard_count: u32,
+    // shards to run in this process instead of just SHARD_ID, either `all`, an
+    // inclusive range like `0-3`, or a bucket like `bucket:1/4` that runs every
+    // 4th shard starting at 1
+    #[serde(default)]
+    pub shards: Option<String>,
+    // claim a shard from the plan the manager keeps in redis instead of using
+    // SHARD_ID and SHARD_COUNT, see `tulpje_shared::shard_plan`
+    #[serde(default)]
+    pub shard_plan: bool,
     pub rabbitmq_address: String,
     pub redis_url: String,
+
+    // route events of a guild to the same handler, see `tulpje_shared::routing`
+    #[serde(default)]
+    pub partition_events: bool,
+    #[serde(default = "default_handler_count")]
+    pub handler_count: u32,
+
+    // zstd compress event payloads, handlers decompress based on the envelope
+    // headers so this can be changed without touching them
+    #[serde(default)]
+    pub compress_events: bool,
+
+    // comma separated `Intents` names to identify with
+    #[serde(default)]
+    pub intents: Option<String>,
+    // comma separated dispatch event names, like `MESSAGE_CREATE`, to publish,
+    // everything is published if unset
+    #[serde(default)]
+    pub forward_events: Option<String>,
+
+    // keep guilds, channels, roles, emojis, members and recent messages in
+    // redis for handlers, see `tulpje_shared::cache`
+    #[serde(default)]
+    pub cache_events: bool,
+}
+
+fn default_shard_count() -> u32 {
+    1
+}
+
+fn default_handler_count() -> u32 {
+    1
 }
 
 impl Config {
     pub fn from_env() -> Result<Self, Error> {
         serde_envfile::from_env()
     }
+
+    pub fn intents(&self) -> Result<Intents, String> {
+        split_names(self.intents.as_deref().unwrap_or(DEFAULT_INTENTS))
+            .map(|name| match name {
+                // what discord calls the intent nowadays
+                "GUILD_EXPRESSIONS" => Ok(Intents::GUILD_EMOJIS_AND_STICKERS),
+                name => Intents::from_name(name).ok_or_else(|| format!("unknown intent: {}", name)),
+            })
+            .collect()
+    }
+
+    pub fn shard_ids(&self) -> Result<Vec<u32>, String> {
+        let ids: Vec<u32> = match self.shards.as_deref().map(str::trim) {
+            None | Some("") => vec![self.shard_id],
+            Some("all") => (0..self.shard_count).collect(),
+            Some(shards) => {
+                if let Some(bucket) = shards.strip_prefix("bucket:") {
+                    let (bucket_id, concurrency) = parse_pair(bucket, '/')?;
+                    if concurrency == 0 || bucket_id >= concurrency {
+                        return Err(format!("invalid bucket: {}", shards));
+                    }
+
+                    (bucket_id..self.shard_count)
+                        .step_by(concurrency as usize)
+                        .collect()
+                } else {
+                    let (start, end) = parse_pair(shards, '-')?;
+                    (start..=end).collect()
+                }
+            }
+        };
+
+        if ids.is_empty() {
+            return Err("no shards to run".into());
+        }
+        if let Some(id) = ids.iter().find(|id| **id >= self.shard_count) {
+            return Err(format!(
+                "shard {} out of range for shard count {}",
+                id, self.shard_count
+            ));
+        }
+
+        Ok(ids)
+    }
+
+    pub fn forward_events(&self) -> Result<Option<EventTypeFlags>, String> {
+        self.forward_events
+            .as_deref()
+            .map(|names| {
+                split_names(names)
+                    .map(|name| {
+                        EventType::try_from(name)
+                            .map(EventTypeFlags::from)
+                            .map_err(|_| format!("unknown event: {}", name))
+                    })
+                    .collect()
+            })
+            .transpose()
+    }
+}
+
+fn parse_pair(value: &str, separator: char) -> Result<(u32, u32), String> {
+    let parse = |part: &str| {
+        part.trim()
+            .parse::<u32>()
+            .map_err(|_| format!("invalid shards: {}", value))
+    };
+
+    let Some((first, second)) = value.split_once(separator) else {
+        return Err(format!("invalid shards: {}", value));
+    };
+
+    Ok((parse(first)?, parse(second)?))
+}
+
+fn split_names(names: &str) -> impl Iterator<Item = &str> {
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(shards: Option<&str>) -> Config {
+        Config {
+            discord_token: String::new(),
+            discord_proxy: String::new(),
+            discord_gateway_queue: String::new(),
+            shard_id: 2,
+            shard_count: 8,
+            shards: shards.map(ToOwned::to_owned),
+            shard_plan: false,
+            rabbitmq_address: String::new(),
+            redis_url: String::new(),
+            partition_events: false,
+            handler_count: 1,
+            compress_events: false,
+            intents: None,
+            forward_events: None,
+            cache_events: false,
+        }
+    }
+
+    #[test]
+    fn shard_ids_test() {
+        assert_eq!(config(None).shard_ids(), Ok(vec![2]));
+        assert_eq!(config(Some("all")).shard_ids(), Ok((0..8).collect()));
+        assert_eq!(config(Some("2-4")).shard_ids(), Ok(vec![2, 3, 4]));
+        assert_eq!(config(Some("bucket:1/4")).shard_ids(), Ok(vec![1, 5]));
+
+        assert!(config(Some("4-2")).shard_ids().is_err());
+        assert!(config(Some("6-8")).shard_ids().is_err());
+        assert!(config(Some("bucket:4/4")).shard_ids().is_err());
+        assert!(config(Some("bucket:1/0")).shard_ids().is_err());
+        assert!(config(Some("three")).shard_ids().is_err());
+    }
 }
@@ -1,23 +1,37 @@
-use std::{env, error::Error};
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    time::{Duration, Instant},
+};
 
 use bb8_redis::RedisConnectionManager;
 use futures_util::StreamExt;
-use twilight_gateway::EventTypeFlags;
+use tokio::sync::watch;
+use twilight_gateway::{EventTypeFlags, Shard, ShardId};
 use twilight_model::gateway::{
     event::{Event, GatewayEventDeserializer},
     payload::outgoing::{identify::IdentifyProperties, update_presence::UpdatePresencePayload},
     presence::{Activity, MinimalActivity, Status},
-    OpCode,
+    CloseFrame, OpCode,
 };
 
-use tulpje_shared::DiscordEvent;
+use tulpje_shared::{amqp::BufferedPublisher, envelope, routing, shutdown, DiscordEvent};
 
 mod amqp;
+mod cache;
 mod config;
 mod metrics;
+mod queue;
+mod session;
+mod shard_plan;
 mod shard_state;
 
 use config::Config;
+use queue::GatewayQueue;
+
+// how long buffered events get to be published when shutting down
+const AMQP_CLOSE_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -45,124 +59,385 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // create config from environment vars
     let config = Config::from_env()?;
+    let intents = config.intents()?;
+    let forward_events = config.forward_events()?;
 
     // set-up logging
     tracing_subscriber::fmt::init();
 
-    let amqp = amqp::create(&config.rabbitmq_address).await;
+    // shards close when we're asked to shut down
+    let (shutdown_send, mut shutdown_recv) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown::signal().await;
+        tracing::info!("shutting down...");
+        shutdown_send.send_replace(true);
+    });
+
+    let amqp = amqp::create(&config.rabbitmq_address);
 
     // create the redis connection
-    let manager = RedisConnectionManager::new(config.redis_url).expect("error initialising redis");
+    let manager =
+        RedisConnectionManager::new(config.redis_url.clone()).expect("error initialising redis");
     let redis = bb8::Pool::builder()
         .build(manager)
         .await
         .expect("error initialising redis pool");
 
+    // claim a shard from the shard plan if we're following it
+    let mut assignment = if config.shard_plan {
+        if config.shards.is_some() {
+            tracing::warn!("following the shard plan, ignoring SHARDS");
+        }
+
+        tracing::info!("claiming shard from the shard plan...");
+        let process = format!("gateway-{}", uuid::Uuid::now_v7());
+        tokio::select! {
+            assignment = shard_plan::Assignment::claim(redis.clone(), process) => Some(assignment),
+            _ = shutdown_recv.changed() => return Ok(()),
+        }
+    } else {
+        None
+    };
+    let (shard_ids, shard_count) = match &assignment {
+        Some(assignment) => (vec![assignment.shard_id], assignment.shard_count),
+        None => (config.shard_ids()?, config.shard_count),
+    };
+
     // set-up metrics
     tracing::info!("installing metrics collector and exporter...");
-    metrics::install(redis.clone(), config.shard_id).expect("error setting up metrics");
+    let process_name = match &assignment {
+        Some(assignment) => assignment.process.clone(),
+        None => metrics::process_name(&shard_ids),
+    };
+    metrics::install(redis.clone(), process_name.clone(), &shard_ids)
+        .expect("error setting up metrics");
+
+    // the discord cache is only kept up-to-date when enabled, handlers fall
+    // back to the http api otherwise
+    let discord_cache = config
+        .cache_events
+        .then(|| cache::DiscordCache::new(redis.clone(), intents));
+    if let Some(discord_cache) = &discord_cache {
+        tokio::spawn(discord_cache.clone().heartbeat());
+    }
 
-    // create the shard
-    tracing::info!("shard: {}, total: {}", config.shard_id, config.shard_count);
-    let shard_config = twilight_gateway::ConfigBuilder::new(
-        config.discord_token,
-        twilight_gateway::Intents::all(),
+    // create the shards
+    tracing::info!("shards: {:?}, total: {}", shard_ids, shard_count);
+    tracing::info!(?intents, "identifying with intents");
+    let shard_config = twilight_gateway::ConfigBuilder::new(config.discord_token.clone(), intents)
+        .presence(create_presence())
+        .identify_properties(IdentifyProperties {
+            browser: "tulpje".into(),
+            device: "tulpje".into(),
+            os: std::env::consts::OS.into(),
+        })
+        .queue(GatewayQueue::new(config.discord_gateway_queue.clone()))
+        .build();
+
+    // sessions saved by the previous process, so we can resume instead of
+    // identifying again
+    let saved_sessions = session::take_all(
+        &redis,
+        &shard_ids
+            .iter()
+            .map(|shard_id| ShardId::new(*shard_id, shard_count))
+            .collect::<Vec<_>>(),
     )
-    .presence(create_presence())
-    .identify_properties(IdentifyProperties {
-        browser: "tulpje".into(),
-        device: "tulpje".into(),
-        os: std::env::consts::OS.into(),
-    })
-    .build();
-    let shard_id = twilight_gateway::ShardId::new_checked(config.shard_id, config.shard_count)
-        .expect("error constructing shard ID");
-    let mut shard = twilight_gateway::Shard::with_config(shard_id, shard_config);
-
-    // create shard state manager
-    let mut shard_state_manager = shard_state::ShardManager::new(redis.clone(), shard_id.number());
-
-    // initialisation done, ratelimit on session_limit
-    tracing::info!("waiting for gateway queue...");
-    reqwest::get(config.discord_gateway_queue).await?;
-
-    // start main loop
-    tracing::info!("starting main loop...");
-    loop {
-        match shard.next().await {
-            Some(Ok(twilight_gateway::Message::Close(frame))) => {
-                tracing::warn!(?frame, "gateway connection closed");
-
-                // have to handle this hear separate as twilight_gateway::parse doesn't
-                // parse into Event::GatewayClose as that's a separate event type
-                if let Err(err) = shard_state_manager
-                    .handle_event(Event::GatewayClose(frame), shard.latency())
-                    .await
-                {
-                    tracing::error!("error updating shard state: {}", err);
-                }
+    .await
+    .unwrap_or_else(|err| {
+        tracing::error!("error loading saved sessions: {}", err);
+        HashMap::new()
+    });
+
+    let shards = twilight_gateway::create_iterator(
+        shard_ids.into_iter(),
+        shard_count,
+        shard_config,
+        |shard_id, builder| {
+            let Some(saved) = saved_sessions.get(&shard_id.number()).cloned() else {
+                return builder.build();
+            };
+
+            tracing::info!(shard = shard_id.number(), "resuming saved session");
+            let mut builder = builder.session(saved.session);
+            if let Some(resume_url) = saved.resume_url {
+                builder = builder.resume_url(resume_url);
             }
-            Some(Ok(twilight_gateway::Message::Text(text))) => {
-                let opcode = match parse_opcode(&text) {
-                    Err(err) => {
-                        tracing::error!(?err, "couldn't parse opcode");
-                        continue;
-                    }
-                    Ok(Some(opcode)) => opcode,
-                    Ok(None) => {
-                        tracing::error!("received empty opcode");
-                        continue;
-                    }
-                };
+            builder.build()
+        },
+    );
 
-                tracing::debug!(?opcode, "opcode received");
+    let gateway = Gateway {
+        config,
+        forward_events,
+        amqp,
+        redis,
+        discord_cache,
+        process_name,
+        shutdown: shutdown_recv,
+    };
 
-                if let Ok(Some(event)) =
-                    twilight_gateway::parse(text.clone(), EventTypeFlags::all())
-                {
-                    let event = twilight_model::gateway::event::Event::from(event);
+    // every shard runs its own loop, all on this task as they're mostly
+    // waiting on the network anyway
+    futures_util::future::join_all(shards.map(|shard| gateway.run_shard(shard, assignment.take())))
+        .await;
 
-                    // track event metrics
-                    metrics::track_gateway_event(shard_id.number(), &event);
+    // publish what's left before exiting
+    if let Err(err) = gateway.amqp.close(AMQP_CLOSE_TIMEOUT).await {
+        tracing::error!("error closing amqp publisher: {}", err);
+    }
 
+    Ok(())
+}
+
+// why a shard stopped running
+enum Stop {
+    Retired,
+    Shutdown,
+}
+
+// what the shards of this process share
+struct Gateway {
+    config: Config,
+    forward_events: Option<EventTypeFlags>,
+    amqp: BufferedPublisher,
+    redis: bb8::Pool<RedisConnectionManager>,
+    discord_cache: Option<cache::DiscordCache>,
+    process_name: String,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl Gateway {
+    async fn run_shard(
+        &self,
+        mut shard: Shard<GatewayQueue>,
+        mut assignment: Option<shard_plan::Assignment>,
+    ) {
+        let shard_id = shard.id();
+        let mut shard_state_manager = shard_state::ShardManager::new(
+            self.redis.clone(),
+            shard_id.number(),
+            shard_id.total(),
+            self.process_name.clone(),
+        );
+
+        let mut shutdown = self.shutdown.clone();
+
+        tracing::info!(shard = shard_id.number(), "starting main loop...");
+        let mut last_saved = Instant::now();
+        let stop = loop {
+            // events are published according to the plan from here on
+            if let Some(assignment) = assignment.as_mut() {
+                assignment.acknowledge_publishing().await;
+            }
+
+            if last_saved.elapsed() >= session::SAVE_INTERVAL {
+                if let Err(err) = session::save(&self.redis, &shard).await {
+                    tracing::error!("error saving session: {}", err);
+                }
+                last_saved = Instant::now();
+            }
+
+            let message = tokio::select! {
+                message = shard.next() => message,
+                () = plan_changed(assignment.as_mut()) => {
+                    if assignment.as_ref().is_some_and(shard_plan::Assignment::retired) {
+                        tracing::info!("shard retired or claim lost, shutting down");
+                        break Stop::Retired;
+                    }
+                    continue;
+                }
+                _ = shutdown.changed() => break Stop::Shutdown,
+            };
+
+            match message {
+                Some(Ok(twilight_gateway::Message::Close(frame))) => {
+                    tracing::warn!(
+                        shard = shard_id.number(),
+                        ?frame,
+                        "gateway connection closed"
+                    );
+
+                    // have to handle this hear separate as twilight_gateway::parse doesn't
+                    // parse into Event::GatewayClose as that's a separate event type
                     if let Err(err) = shard_state_manager
-                        .handle_event(event.clone(), shard.latency())
+                        .handle_event(Event::GatewayClose(frame), shard.latency())
                         .await
                     {
                         tracing::error!("error updating shard state: {}", err);
                     }
                 }
-
-                // only publish non-gateway events, aka everything DISPATCH
-                if opcode == OpCode::Dispatch {
-                    let event = DiscordEvent::new(shard_id.number(), text);
-                    let serialized_event = match serde_json::to_vec(&event) {
-                        Ok(val) => val,
+                Some(Ok(twilight_gateway::Message::Text(text))) => {
+                    let (opcode, event_name) = match parse_opcode(&text) {
                         Err(err) => {
-                            tracing::error!("error serializing event: {}", err);
+                            tracing::error!(?err, "couldn't parse opcode");
+                            continue;
+                        }
+                        Ok((Some(opcode), event_name)) => (opcode, event_name),
+                        Ok((None, _)) => {
+                            tracing::error!("received empty opcode");
                             continue;
                         }
                     };
 
-                    if let Err(err) = amqp.send(&serialized_event).await {
-                        tracing::error!("error sending event to amqp: {}", err);
-                        continue;
+                    tracing::debug!(?opcode, "opcode received");
+
+                    let parsed = twilight_gateway::parse(text.clone(), EventTypeFlags::all())
+                        .ok()
+                        .flatten()
+                        .map(Event::from);
+
+                    if let Some(event) = &parsed {
+                        // track event metrics
+                        metrics::track_gateway_event(shard_id.number(), event);
+                        if matches!(event, Event::GatewayHeartbeatAck) {
+                            metrics::track_latency(shard_id.number(), shard.latency());
+                        }
+
+                        if let Err(err) = shard_state_manager
+                            .handle_event(event.clone(), shard.latency())
+                            .await
+                        {
+                            tracing::error!("error updating shard state: {}", err);
+                        }
+
+                        // lets the manager know when the new shards are up
+                        if let (Event::Ready(_), Some(assignment)) = (event, &assignment) {
+                            assignment.ready().await;
+                        }
+
+                        // update the cache before publishing, so handlers see the
+                        // state after the event
+                        if let Some(discord_cache) = &self.discord_cache {
+                            if let Err(err) = discord_cache.handle_event(event).await {
+                                tracing::error!("error updating discord cache: {}", err);
+                            }
+                        }
                     }
 
-                    tracing::debug!(
-                        uuid = ?event.meta.uuid,
-                        shard = event.meta.shard,
-                        "event sent"
+                    // only publish non-gateway events, aka everything DISPATCH
+                    if opcode == OpCode::Dispatch {
+                        let Some(event_name) = event_name else {
+                            tracing::error!("received dispatch without event name");
+                            continue;
+                        };
+
+                        // while resharding only one generation of shards publishes
+                        if assignment
+                            .as_ref()
+                            .is_some_and(|assignment| !assignment.publishes())
+                        {
+                            tracing::trace!(event = %event_name, "event not published by this generation");
+                            continue;
+                        }
+
+                        self.publish(shard_id.number(), &event_name, text, parsed.as_ref());
+                    }
+                }
+                Some(Err(err)) => {
+                    tracing::error!(
+                        shard = shard_id.number(),
+                        ?err,
+                        "error receiving discord message"
                     );
                 }
+                None => {
+                    tracing::error!(shard = shard_id.number(), "received empty message");
+                }
+            };
+        };
+
+        match stop {
+            // the session of a retired shard is never resumed
+            Stop::Retired => {
+                close_shard(&mut shard, CloseFrame::NORMAL).await;
+                if let Err(err) = session::delete(&self.redis, shard_id).await {
+                    tracing::error!("error deleting session: {}", err);
+                }
             }
-            Some(Err(err)) => {
-                tracing::error!(?err, "error receiving discord message");
+            // saved before closing, events received while closing aren't
+            // published and get replayed when the session is resumed
+            Stop::Shutdown => {
+                if let Err(err) = session::save(&self.redis, &shard).await {
+                    tracing::error!("error saving session: {}", err);
+                }
+                close_shard(&mut shard, CloseFrame::RESUME).await;
+            }
+        }
+        if let Err(err) = shard_state_manager.shut_down().await {
+            tracing::error!("error updating shard state: {}", err);
+        }
+        if let Some(assignment) = &assignment {
+            assignment.release().await;
+        }
+    }
+
+    fn publish(&self, shard_id: u32, event_name: &str, text: String, parsed: Option<&Event>) {
+        // don't publish events nobody handles
+        if let Some(forward_events) = &self.forward_events {
+            let forwarded = EventTypeFlags::try_from((OpCode::Dispatch, Some(event_name)))
+                .is_ok_and(|flags| forward_events.contains(flags));
+            if !forwarded {
+                tracing::trace!(event = %event_name, "event not forwarded");
+                return;
             }
-            None => {
-                tracing::error!("received empty message");
+        }
+
+        let event = DiscordEvent::new(shard_id, text);
+        let (headers, data) = match envelope::encode(&event, self.config.compress_events) {
+            Ok(val) => val,
+            Err(err) => {
+                tracing::error!("error encoding event: {}", err);
+                return;
             }
         };
+
+        // events without a guild all go to the first partition
+        let partition = self.config.partition_events.then(|| {
+            parsed.and_then(Event::guild_id).map_or(0, |guild_id| {
+                routing::partition(guild_id.get(), self.config.handler_count)
+            })
+        });
+        let routing_key = routing::routing_key(event_name, event.meta.shard, partition);
+        if let Err(err) = self.amqp.send(routing_key, headers, data) {
+            tracing::error!("error sending event to amqp: {}", err);
+            return;
+        }
+
+        tracing::debug!(
+            uuid = ?event.meta.uuid,
+            shard = event.meta.shard,
+            "event queued"
+        );
+    }
+}
+
+// waits for the shard plan to change, never resolves if we're not following it
+async fn plan_changed(assignment: Option<&mut shard_plan::Assignment>) {
+    let Some(assignment) = assignment else {
+        return std::future::pending().await;
+    };
+
+    assignment.changed().await;
+}
+
+// closes the connection and waits for discord to acknowledge it
+async fn close_shard(shard: &mut Shard<GatewayQueue>, frame: CloseFrame<'static>) {
+    shard.close(frame);
+
+    let closed = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(message) = shard.next().await {
+            if let Ok(twilight_gateway::Message::Close(_)) = message {
+                break;
+            }
+        }
+    })
+    .await;
+    if closed.is_err() {
+        tracing::warn!(
+            shard = shard.id().number(),
+            "timed out waiting for the gateway connection to close"
+        );
     }
 }
 
@@ -189,10 +464,14 @@ fn create_presence() -> UpdatePresencePayload {
         .expect("couldn't create UpdatePresence struct")
 }
 
-fn parse_opcode(event: &str) -> Result<Option<OpCode>, Box<dyn Error>> {
+// opcode and event name of a gateway event, only dispatches have an event name
+fn parse_opcode(event: &str) -> Result<(Option<OpCode>, Option<String>), Box<dyn Error>> {
     let Some(gateway_deserializer) = GatewayEventDeserializer::from_json(event) else {
         return Err("couldn't deserialise event".into());
     };
 
-    Ok(OpCode::from(gateway_deserializer.op()))
+    Ok((
+        OpCode::from(gateway_deserializer.op()),
+        gateway_deserializer.event_type().map(ToOwned::to_owned),
+    ))
 }
@@ -1,23 +1,37 @@
 use std::error::Error;
 
 use bb8_redis::RedisConnectionManager;
-use metrics::{counter, describe_counter};
+use metrics::{counter, describe_counter, describe_gauge, gauge};
 use metrics_exporter_prometheus::PrometheusBuilder;
-use twilight_gateway::{Event, EventType};
+use twilight_gateway::{Event, EventType, Latency};
+
+// processes not following the shard plan are named after the first shard they
+// run, they never run the same shards
+pub(crate) fn process_name(shard_ids: &[u32]) -> String {
+    format!("gateway-{}", shard_ids.first().copied().unwrap_or_default())
+}
 
 pub(crate) fn install(
     redis: bb8::Pool<RedisConnectionManager>,
-    shard_id: u32,
+    process_name: String,
+    shard_ids: &[u32],
 ) -> Result<(), Box<dyn Error>> {
     // install metrics collector and exporter
-    tulpje_shared::metrics::install(
-        PrometheusBuilder::new(),
-        redis,
-        format!("gateway-{}", shard_id),
-    )?;
+    tulpje_shared::metrics::install(PrometheusBuilder::new(), redis, process_name)?;
 
     // define metrics
     describe_counter!("gateway_events", "Discord Gateway Events");
+    describe_gauge!("gateway_shards", "Shards run by this gateway");
+    describe_gauge!(
+        "gateway_latency",
+        "Latency of the last heartbeat in milliseconds"
+    );
+
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "we'll never run enough shards in one process to lose precision"
+    )]
+    gauge!("gateway_shards").set(shard_ids.len() as f64);
 
     Ok(())
 }
@@ -43,3 +57,11 @@ pub(crate) fn track_gateway_event(shard: u32, event: &Event) {
     )
     .increment(1);
 }
+
+pub(crate) fn track_latency(shard: u32, latency: &Latency) {
+    let Some(recent) = latency.recent().first() else {
+        return;
+    };
+
+    gauge!("gateway_latency", "shard" => shard.to_string()).set(recent.as_secs_f64() * 1000.);
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use twilight_gateway::queue::Queue;
+
+// how long to wait before retrying when the gateway queue can't be reached
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// ratelimits on session_limit through the gateway queue service, so shards of
+// all gateways share it, the shard goes through it every time it identifies,
+// including after its session was invalidated, resuming doesn't count towards
+// the limit
+#[derive(Clone, Debug)]
+pub(crate) struct GatewayQueue {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl GatewayQueue {
+    pub(crate) fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    async fn wait(&self, shard_id: u32) -> Result<(), reqwest::Error> {
+        self.client
+            .get(&self.url)
+            .query(&[("shard", shard_id)])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+impl Queue for GatewayQueue {
+    fn enqueue(&self, shard_id: u32) -> oneshot::Receiver<()> {
+        let (send, recv) = oneshot::channel();
+        let queue = self.clone();
+
+        tokio::spawn(async move {
+            tracing::info!(shard = shard_id, "waiting for gateway queue...");
+            match queue.wait(shard_id).await {
+                Ok(()) => {
+                    // the shard might've been dropped in the meantime
+                    let _ = send.send(());
+                }
+                // dropping the sender makes the shard enqueue again
+                Err(err) => {
+                    tracing::error!(shard = shard_id, "error waiting for gateway queue: {}", err);
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        });
+
+        recv
+    }
+}
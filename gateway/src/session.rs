@@ -0,0 +1,100 @@
+use std::{collections::HashMap, time::Duration};
+
+use bb8_redis::{redis::AsyncCommands as _, RedisConnectionManager};
+use serde::{Deserialize, Serialize};
+use twilight_gateway::{Session, Shard, ShardId};
+
+use crate::queue::GatewayQueue;
+
+// how long a saved session can be resumed from, discord invalidates sessions
+// that have been disconnected for too long anyway, in seconds
+const SESSION_TTL: u64 = 120;
+
+// how often the session is saved while running, a crashed gateway resumes from
+// the last save so a few events might be received twice
+pub(crate) const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+// what a shard needs to RESUME instead of IDENTIFY after a restart
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct SavedSession {
+    pub(crate) session: Session,
+    pub(crate) resume_url: Option<String>,
+}
+
+// sessions are only valid for the shard count they were created with
+fn session_key(shard_id: ShardId) -> String {
+    format!(
+        "tulpje:gateway_session:{}:{}",
+        shard_id.total(),
+        shard_id.number()
+    )
+}
+
+// removes saved sessions while loading them, so two processes never resume the
+// same session
+pub(crate) async fn take_all(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    shard_ids: &[ShardId],
+) -> Result<HashMap<u32, SavedSession>, Box<dyn std::error::Error>> {
+    let mut conn = redis.get().await?;
+    let mut sessions = HashMap::new();
+
+    for shard_id in shard_ids {
+        let Some(json) = conn
+            .get_del::<String, Option<String>>(session_key(*shard_id))
+            .await?
+        else {
+            continue;
+        };
+
+        match serde_json::from_str(&json) {
+            Ok(session) => {
+                sessions.insert(shard_id.number(), session);
+            }
+            Err(err) => tracing::warn!(
+                shard = shard_id.number(),
+                "error decoding saved session: {}",
+                err
+            ),
+        }
+    }
+
+    Ok(sessions)
+}
+
+// clears the saved session if the shard doesn't have one anymore, so we don't
+// try to resume an invalidated session
+pub(crate) async fn save(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    shard: &Shard<GatewayQueue>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(session) = shard.session() else {
+        return delete(redis, shard.id()).await;
+    };
+
+    let json = serde_json::to_string(&SavedSession {
+        session: session.clone(),
+        resume_url: shard.resume_url().map(ToOwned::to_owned),
+    })?;
+
+    redis
+        .get()
+        .await?
+        .set_ex::<String, String, ()>(session_key(shard.id()), json, SESSION_TTL)
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn delete(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    shard_id: ShardId,
+) -> Result<(), Box<dyn std::error::Error>> {
+    redis
+        .get()
+        .await?
+        .del::<String, ()>(session_key(shard_id))
+        .await?;
+
+    Ok(())
+}
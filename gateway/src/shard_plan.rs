@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use bb8_redis::RedisConnectionManager;
+use tokio::sync::watch;
+
+use tulpje_shared::shard_plan::{self, Phase, ShardPlan, CLAIM_TTL};
+
+// how often the plan is reloaded and the claim renewed
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// a shard claimed from the shard plan the manager keeps in redis, see
+// `tulpje_shared::shard_plan`
+pub(crate) struct Assignment {
+    pub(crate) generation: u64,
+    pub(crate) shard_id: u32,
+    pub(crate) shard_count: u32,
+    // unique per process, so also used as the metrics name
+    pub(crate) process: String,
+
+    redis: bb8::Pool<RedisConnectionManager>,
+    state: watch::Receiver<State>,
+    // whether we told the manager we took over publishing
+    acknowledged: bool,
+}
+
+// what `Assignment::follow` keeps up-to-date
+struct State {
+    plan: ShardPlan,
+    // the claim expired, another process might be running our shard by now
+    claim_lost: bool,
+}
+
+impl Assignment {
+    // waits until there's a plan with an unclaimed shard
+    pub(crate) async fn claim(redis: bb8::Pool<RedisConnectionManager>, process: String) -> Self {
+        loop {
+            match Self::try_claim(&redis, &process).await {
+                Ok(Some((plan, shard_id))) => {
+                    let set = plan.joining();
+                    tracing::info!(
+                        generation = set.generation,
+                        shard_id,
+                        shard_count = set.shard_count,
+                        "claimed shard"
+                    );
+
+                    let (state_send, state_recv) = watch::channel(State {
+                        plan,
+                        claim_lost: false,
+                    });
+                    tokio::spawn(Self::follow(
+                        redis.clone(),
+                        process.clone(),
+                        set.generation,
+                        shard_id,
+                        state_send,
+                    ));
+
+                    return Self {
+                        generation: set.generation,
+                        shard_id,
+                        shard_count: set.shard_count,
+                        redis,
+                        process,
+                        state: state_recv,
+                        acknowledged: false,
+                    };
+                }
+                Ok(None) => tracing::warn!("no shard to claim, waiting..."),
+                Err(err) => tracing::error!("error claiming shard: {}", err),
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn try_claim(
+        redis: &bb8::Pool<RedisConnectionManager>,
+        process: &str,
+    ) -> Result<Option<(ShardPlan, u32)>, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(plan) = shard_plan::load(redis).await? else {
+            return Ok(None);
+        };
+
+        Ok(shard_plan::claim(redis, plan.joining(), process)
+            .await?
+            .map(|shard_id| (plan, shard_id)))
+    }
+
+    // keeps the plan up-to-date and renews the claim until we're retired or
+    // the claim is lost
+    async fn follow(
+        redis: bb8::Pool<RedisConnectionManager>,
+        process: String,
+        generation: u64,
+        shard_id: u32,
+        state_send: watch::Sender<State>,
+    ) {
+        let mut last_renewed = tokio::time::Instant::now();
+
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            match shard_plan::load(&redis).await {
+                Ok(Some(plan)) => {
+                    let retired = plan.retired(generation);
+                    state_send.send_if_modified(|state| {
+                        let modified = state.plan != plan;
+                        state.plan = plan;
+                        modified
+                    });
+                    if retired {
+                        return;
+                    }
+                }
+                Ok(None) => tracing::warn!("shard plan disappeared"),
+                Err(err) => tracing::error!("error loading shard plan: {}", err),
+            }
+
+            if last_renewed.elapsed() >= Duration::from_secs(CLAIM_TTL / 3) {
+                let lost =
+                    match shard_plan::renew_claim(&redis, generation, shard_id, &process).await {
+                        Ok(renewed) => {
+                            last_renewed = tokio::time::Instant::now();
+                            !renewed
+                        }
+                        Err(err) => {
+                            tracing::error!("error renewing shard claim: {}", err);
+                            // the claim expires if we can't renew it in time
+                            last_renewed.elapsed() >= Duration::from_secs(CLAIM_TTL)
+                        }
+                    };
+
+                if lost {
+                    tracing::error!(generation, shard_id, "lost shard claim");
+                    state_send.send_modify(|state| state.claim_lost = true);
+                    return;
+                }
+            }
+        }
+    }
+
+    // whether our events should be published
+    pub(crate) fn publishes(&self) -> bool {
+        let state = self.state.borrow();
+        !state.claim_lost && state.plan.publishes(self.generation)
+    }
+
+    // whether we should shut down
+    pub(crate) fn retired(&self) -> bool {
+        let state = self.state.borrow();
+        state.claim_lost || state.plan.retired(self.generation)
+    }
+
+    // waits for the plan to change or the claim to be lost
+    pub(crate) async fn changed(&mut self) {
+        if self.state.changed().await.is_err() {
+            // nothing left to follow once the plan stops updating
+            std::future::pending::<()>().await;
+        }
+    }
+
+    // lets the manager know we're publishing while taking over from the
+    // previous generation, so it can retire that one, only call this once
+    // events are published according to the current plan
+    pub(crate) async fn acknowledge_publishing(&mut self) {
+        if self.acknowledged {
+            return;
+        }
+
+        let taking_over = {
+            let state = self.state.borrow();
+            !state.claim_lost
+                && state.plan.phase == Phase::CuttingOver
+                && state.plan.publishing().generation == self.generation
+        };
+        if !taking_over {
+            return;
+        }
+
+        match shard_plan::mark_publishing(&self.redis, self.generation, self.shard_id).await {
+            Ok(()) => self.acknowledged = true,
+            Err(err) => tracing::error!("error acknowledging publishing: {}", err),
+        }
+    }
+
+    pub(crate) async fn ready(&self) {
+        if let Err(err) = shard_plan::mark_ready(&self.redis, self.generation, self.shard_id).await
+        {
+            tracing::error!("error marking shard ready: {}", err);
+        }
+    }
+
+    pub(crate) async fn release(&self) {
+        if let Err(err) =
+            shard_plan::release_claim(&self.redis, self.generation, self.shard_id, &self.process)
+                .await
+        {
+            tracing::error!("error releasing shard claim: {}", err);
+        }
+    }
+}
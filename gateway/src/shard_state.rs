@@ -6,7 +6,7 @@ use std::{
 use bb8_redis::{redis::AsyncCommands, RedisConnectionManager};
 use twilight_gateway::{Event, Latency};
 
-use tulpje_shared::shard_state::ShardState;
+use tulpje_shared::shard_state::{self, ShardState, SHARD_STATUS_KEY};
 use twilight_model::gateway::payload::incoming::{GuildCreate, GuildDelete, Hello, Ready};
 
 pub struct ShardManager {
@@ -16,11 +16,16 @@ pub struct ShardManager {
 }
 
 impl ShardManager {
-    pub fn new(redis: bb8::Pool<RedisConnectionManager>, shard_id: u32) -> Self {
+    pub fn new(
+        redis: bb8::Pool<RedisConnectionManager>,
+        shard_id: u32,
+        shard_count: u32,
+        process: String,
+    ) -> Self {
         Self {
             redis,
             guild_ids: HashSet::new(),
-            shard: ShardState::new(shard_id),
+            shard: ShardState::new(shard_id, shard_count, process),
         }
     }
 
@@ -41,15 +46,19 @@ impl ShardManager {
         }
     }
 
-    async fn save_shard(&self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn save_shard(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.shard.updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_secs();
         let json_shard = serde_json::to_string(&self.shard)?;
 
         self.redis
             .get()
             .await?
             .hset::<&str, String, String, ()>(
-                "tulpje:shard_status",
-                self.shard.shard_id.to_string(),
+                SHARD_STATUS_KEY,
+                shard_state::status_field(self.shard.shard_id, self.shard.shard_count),
                 json_shard,
             )
             .await
@@ -145,6 +154,14 @@ impl ShardManager {
         self.save_shard().await
     }
 
+    pub async fn shut_down(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        tracing::info!("shard {} shut down", self.shard.shard_id);
+
+        self.shard.up = false;
+
+        self.save_shard().await
+    }
+
     async fn heartbeated(&mut self, latency: &Latency) -> Result<(), Box<dyn std::error::Error>> {
         self.shard.up = true;
         self.shard.last_heartbeat = SystemTime::now()
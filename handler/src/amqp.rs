@@ -3,13 +3,111 @@ compile_error!(
     "can only pick one amqp implementation, `amqp-amqprs` and `amqp-lapin` are mutually exclusive"
 );
 
-#[cfg(feature = "amqp-amqprs")]
-mod amqprs;
-#[cfg(feature = "amqp-lapin")]
-mod lapin;
+use std::sync::Arc;
+
+use tulpje_framework::{Error, FailedHandlers};
+use tulpje_shared::{
+    amqp::{Amqp, Consumer, Delivery, ExchangeKind, Headers, Topology},
+    dead_letter, routing, DiscordEventMeta,
+};
+
+// how many times the event was requeued because handlers failed
+pub(crate) const HEADER_ATTEMPT: &str = "x-tulpje-attempt";
+// comma separated ids of the handlers that failed, only those are run when the
+// event comes back
+pub(crate) const HEADER_FAILED_HANDLERS: &str = "x-tulpje-failed-handlers";
 
 #[cfg(feature = "amqp-amqprs")]
-pub(crate) use amqprs::create;
+pub(crate) type Backend = tulpje_shared::amqp::amqprs::AmqprsConnection;
 
 #[cfg(feature = "amqp-lapin")]
-pub(crate) use lapin::create;
+pub(crate) type Backend = tulpje_shared::amqp::lapin::LapinConnection;
+
+pub(crate) async fn create(
+    addr: &str,
+    prefetch: u16,
+    queue: &str,
+    bindings: &[String],
+) -> (Arc<Amqp<Backend>>, Consumer) {
+    // only bind the events we handle, bindings for events that are no longer
+    // handled need to be removed manually
+    let mut topology = Topology::new()
+        .exchange(routing::EXCHANGE, ExchangeKind::Topic)
+        .queue(queue);
+    for binding in bindings {
+        topology = topology.bind(queue, routing::EXCHANGE, binding);
+    }
+    // declare the dead-letter exchange and queue
+    let topology = topology
+        .exchange(dead_letter::EXCHANGE, ExchangeKind::Fanout)
+        .queue(dead_letter::QUEUE)
+        .bind(dead_letter::QUEUE, dead_letter::EXCHANGE, "");
+
+    let amqp = Amqp::<Backend>::connect(addr, topology).await;
+    let consumer = amqp.consume(queue, prefetch);
+
+    (amqp, consumer)
+}
+
+// publishes the event back to our queue through the default exchange, with
+// the handlers that failed so only those are run again, and acks it
+pub(crate) async fn requeue(
+    amqp: &Amqp<Backend>,
+    delivery: Delivery,
+    queue: &str,
+    attempt: usize,
+    failed: &FailedHandlers,
+) -> Result<(), Error> {
+    let mut headers = delivery.headers().clone();
+    headers.insert(HEADER_ATTEMPT.to_string(), attempt.to_string());
+    headers.insert(HEADER_FAILED_HANDLERS.to_string(), failed.ids.join(","));
+    amqp.publish("", queue, &headers, delivery.data()).await?;
+
+    delivery.ack().await
+}
+
+// publishes the event to the dead-letter exchange and acks it
+pub(crate) async fn dead_letter(
+    amqp: &Amqp<Backend>,
+    delivery: Delivery,
+    error: &str,
+    meta: Option<&DiscordEventMeta>,
+) -> Result<(), Error> {
+    // keep the envelope headers so the event can be replayed as-is, including
+    // the failed handlers, but it gets all its attempts again when replayed
+    let mut headers = delivery.headers().clone();
+    headers.remove(HEADER_ATTEMPT);
+    headers.extend(dead_letter_headers(error, meta, delivery.routing_key())?);
+    amqp.publish(dead_letter::EXCHANGE, "", &headers, delivery.data())
+        .await?;
+
+    delivery.ack().await
+}
+
+// headers describing why an event was dead-lettered
+fn dead_letter_headers(
+    error: &str,
+    meta: Option<&DiscordEventMeta>,
+    routing_key: &str,
+) -> Result<Headers, Error> {
+    let mut headers = Headers::from([
+        (dead_letter::HEADER_ERROR.to_string(), error.to_string()),
+        (
+            dead_letter::HEADER_ROUTING_KEY.to_string(),
+            routing_key.to_string(),
+        ),
+        (
+            dead_letter::HEADER_FAILED_AT.to_string(),
+            chrono::Utc::now().timestamp().to_string(),
+        ),
+    ]);
+
+    if let Some(meta) = meta {
+        headers.insert(
+            dead_letter::HEADER_META.to_string(),
+            serde_json::to_string(meta)?,
+        );
+    }
+
+    Ok(headers)
+}
@@ -5,11 +5,22 @@ use serde_envfile::Error;
 pub struct Config {
     pub discord_proxy: String,
     pub rabbitmq_address: String,
+    // max amount of unacknowledged events rabbitmq sends us at once
+    #[serde(default = "default_rabbitmq_prefetch")]
+    pub rabbitmq_prefetch: u16,
     pub redis_url: String,
     pub database_url: String,
 
     pub handler_id: u32,
     pub handler_count: u32,
+    // consume from a queue of our own, only getting events for the guilds in
+    // our partition, needs to match the gateway's setting
+    #[serde(default)]
+    pub partition_events: bool,
+}
+
+fn default_rabbitmq_prefetch() -> u16 {
+    10
 }
 
 impl Config {
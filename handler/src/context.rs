@@ -18,6 +18,7 @@ pub struct Services {
 }
 
 pub type Context = context::Context<Services>;
+pub type AutocompleteContext = context::AutocompleteContext<Services>;
 pub type ComponentInteractionContext = context::ComponentInteractionContext<Services>;
 pub type CommandContext = context::CommandContext<Services>;
 pub type EventContext = context::EventContext<Services>;
@@ -13,13 +13,34 @@ use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
     ConnectOptions as _,
 };
+use tokio::sync::watch;
 use tracing::log::LevelFilter;
+use twilight_gateway::EventType;
 
-use tulpje_framework::{Error, Registry, Scheduler};
-use tulpje_shared::{DiscordEvent, DiscordEventMeta};
+use tulpje_framework::{
+    cache::Cache, guild_modules::CachedGuildModuleLookup, Error, FailedHandlers, Registry,
+    Scheduler,
+};
+use tulpje_shared::{amqp::Headers, envelope, routing, shutdown, DiscordEventMeta};
 
 use config::Config;
 
+// how long enabled guild modules are cached for, changes made through another
+// handler instance can take this long to apply
+const GUILD_MODULES_TTL: Duration = Duration::from_secs(60);
+
+// how long to wait before requeueing an event whose handlers failed, once
+// they're all used up the event is dead-lettered instead
+const REQUEUE_DELAYS: [Duration; 3] = [
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+    Duration::from_secs(60),
+];
+
+// how long to wait for the event being handled and running tasks when shutting
+// down, should be less than the time docker gives us before killing us
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(20);
+
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     // load .env into environment vars, ignore if not found
@@ -46,6 +67,13 @@ async fn main() -> Result<(), Error> {
 
     // create config from environment vars
     let config = Config::from_env()?;
+    if config.partition_events && config.handler_id >= config.handler_count {
+        return Err(format!(
+            "handler_id {} is out of range for handler_count {}",
+            config.handler_id, config.handler_count
+        )
+        .into());
+    }
 
     // set-up logging
     tracing_subscriber::fmt::init();
@@ -80,9 +108,6 @@ async fn main() -> Result<(), Error> {
         .await
         .expect("error connecting to db");
 
-    // create AMQP connection
-    let mut amqp = amqp::create(&config.rabbitmq_address).await;
-
     tracing::info!("running migrations...");
     sqlx::migrate!("../migrations")
         .run(&db)
@@ -96,6 +121,18 @@ async fn main() -> Result<(), Error> {
     tracing::info!("registering handlers");
     let mut registry = Registry::<Services>::new();
 
+    registry.set_guild_module_lookup(CachedGuildModuleLookup::new(
+        modules::core::DbGuildModules::new(db.clone()),
+        GUILD_MODULES_TTL,
+    ));
+
+    // application owners, used for owner-only commands
+    let owners: Vec<_> = match &app.team {
+        Some(team) => team.members.iter().map(|member| member.user.id).collect(),
+        None => app.owner.iter().map(|owner| owner.id).collect(),
+    };
+    registry.set_owners(owners);
+
     registry.register(modules::emoji::build());
     registry.register(modules::pk::build());
     registry.register(modules::stats::build());
@@ -107,9 +144,28 @@ async fn main() -> Result<(), Error> {
     // we don't need to mutate registry anymore after this
     let registry = Arc::new(registry);
 
+    // create AMQP connection, only receiving the events we handle
+    let partition = config.partition_events.then_some(config.handler_id);
+    let bindings: Vec<String> = registry
+        .handled_events()
+        .into_iter()
+        .filter_map(EventType::name)
+        .map(|event| routing::binding_key(event, partition))
+        .collect();
+    let queue = routing::queue(partition);
+    let (amqp, mut consumer) = amqp::create(
+        &config.rabbitmq_address,
+        config.rabbitmq_prefetch,
+        &queue,
+        &bindings,
+    )
+    .await;
+
     // create context
+    let client = Arc::new(client);
     let context = context::Context {
         application_id: app.id,
+        cache: Cache::new(redis.clone(), Arc::clone(&client)),
         services: context::Services {
             handler_id: config.handler_id,
 
@@ -117,7 +173,7 @@ async fn main() -> Result<(), Error> {
             db,
             registry: Arc::clone(&registry),
         },
-        client: Arc::new(client),
+        client,
     };
 
     // start the task scheduler
@@ -146,16 +202,35 @@ async fn main() -> Result<(), Error> {
         .await?;
     }
 
-    let main_handle = tokio::spawn(async move {
+    let (shutdown_send, mut shutdown_recv) = watch::channel(false);
+    let mut main_handle = tokio::spawn(async move {
         loop {
-            let Some(message) = amqp.recv().await else {
+            // stop consuming when shutting down, deliveries we haven't acked
+            // yet are redelivered to another handler by rabbitmq
+            let delivery = tokio::select! {
+                delivery = consumer.recv() => delivery,
+                _ = shutdown_recv.changed() => break,
+            };
+            let Some(delivery) = delivery else {
                 break;
             };
 
-            let (meta, event) = match parse_delivery(message) {
+            let (meta, event) = match parse_delivery(delivery.headers(), delivery.data()) {
                 Ok((meta, event)) => (meta, event),
                 Err(err) => {
                     tracing::error!(?err, "couldn't parse delivery");
+
+                    // retrying won't make it parse, so dead-letter it straight away
+                    if let Err(err) = amqp::dead_letter(
+                        &amqp,
+                        delivery,
+                        &format!("couldn't parse delivery: {}", err),
+                        None,
+                    )
+                    .await
+                    {
+                        tracing::error!("error dead-lettering delivery: {}", err);
+                    }
                     continue;
                 }
             };
@@ -167,19 +242,112 @@ async fn main() -> Result<(), Error> {
                 "event received",
             );
 
-            tulpje_framework::handle(meta, context.clone(), &registry, event.clone()).await;
+            // events that come back after being requeued only run the
+            // handlers that failed, so the others don't handle them twice
+            let attempt: usize = delivery
+                .headers()
+                .get(amqp::HEADER_ATTEMPT)
+                .and_then(|attempt| attempt.parse().ok())
+                .unwrap_or(0);
+            let handled = match delivery.headers().get(amqp::HEADER_FAILED_HANDLERS) {
+                Some(ids) => {
+                    let failed = FailedHandlers {
+                        event: event.kind(),
+                        ids: ids.split(',').map(String::from).collect(),
+                    };
+                    tulpje_framework::retry(
+                        meta.clone(),
+                        context.clone(),
+                        &registry,
+                        event,
+                        &failed,
+                    )
+                    .await
+                }
+                None => {
+                    tulpje_framework::handle(meta.clone(), context.clone(), &registry, event).await
+                }
+            };
+
+            let result = match (handled, REQUEUE_DELAYS.get(attempt)) {
+                (Ok(()), _) => delivery.ack().await,
+                // requeue the event after a while in case the error is
+                // transient, we keep the delivery until then so it's
+                // redelivered by rabbitmq if we stop in the meantime
+                (Err(failed), Some(&delay)) => {
+                    tracing::warn!(
+                        uuid = ?meta.uuid,
+                        "error handling event, requeueing in {:?}: {}",
+                        delay,
+                        failed
+                    );
+
+                    let amqp = Arc::clone(&amqp);
+                    let queue = queue.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(delay).await;
+                        if let Err(err) =
+                            amqp::requeue(&amqp, delivery, &queue, attempt + 1, &failed).await
+                        {
+                            tracing::error!(uuid = ?meta.uuid, "error requeueing event: {}", err);
+                        }
+                    });
+                    continue;
+                }
+                // still failing after all attempts, so the error probably
+                // isn't transient, dead-letter the event
+                (Err(err), None) => {
+                    tracing::warn!(
+                        uuid = ?meta.uuid,
+                        "error handling event, dead-lettering: {}",
+                        err
+                    );
+                    amqp::dead_letter(&amqp, delivery, &err.to_string(), Some(&meta)).await
+                }
+            };
+
+            if let Err(err) = result {
+                tracing::error!(uuid = ?meta.uuid, "error acknowledging delivery: {}", err);
+            }
         }
     });
 
-    futures_util::future::join_all([main_handle, sched_handle]).await;
+    let consuming = tokio::select! {
+        () = shutdown::signal() => true,
+        _ = &mut main_handle => {
+            tracing::error!("stopped consuming events");
+            false
+        }
+    };
+
+    tracing::info!("shutting down...");
+    sched_handle.abort();
+    shutdown_send.send_replace(true);
+
+    let drained = tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+        if consuming {
+            if let Err(err) = main_handle.await {
+                tracing::error!("error stopping event handler: {}", err);
+            }
+        }
+        scheduler.wait_for_tasks().await;
+    })
+    .await;
+    if drained.is_err() {
+        tracing::warn!(
+            "timed out after {}s waiting for handlers and tasks",
+            SHUTDOWN_TIMEOUT.as_secs()
+        );
+    }
 
     Ok(())
 }
 
 fn parse_delivery(
-    message: Vec<u8>,
-) -> Result<(DiscordEventMeta, twilight_model::gateway::event::Event), Box<dyn std::error::Error>> {
-    let discord_event = serde_json::from_str::<DiscordEvent>(&String::from_utf8(message)?)?;
+    headers: &Headers,
+    data: &[u8],
+) -> Result<(DiscordEventMeta, twilight_model::gateway::event::Event), Error> {
+    let discord_event = envelope::decode(headers, data)?;
 
     Ok((
         discord_event.meta,
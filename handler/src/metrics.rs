@@ -1,5 +1,5 @@
 use bb8_redis::RedisConnectionManager;
-use metrics_exporter_prometheus::PrometheusBuilder;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
 
 pub(crate) fn install(
     redis: bb8::Pool<RedisConnectionManager>,
@@ -7,13 +7,16 @@ pub(crate) fn install(
 ) -> Result<(), Box<dyn std::error::Error>> {
     // install metrics collector and exporter
     tulpje_shared::metrics::install(
-        PrometheusBuilder::new(),
+        PrometheusBuilder::new().set_buckets_for_metric(
+            Matcher::Full("handler_duration_seconds".to_string()),
+            tulpje_framework::metrics::DURATION_BUCKETS,
+        )?,
         redis,
         format!("handler-{}", handler_id),
     )?;
 
     // define metrics
-    // ..
+    tulpje_framework::metrics::describe();
 
     Ok(())
 }
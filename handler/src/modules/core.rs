@@ -1,23 +1,31 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, future::Future, pin::Pin};
 
 use twilight_http::client::InteractionClient;
 use twilight_model::{
-    application::command::{Command, CommandType},
+    application::command::{Command, CommandOption, CommandType},
     guild::Permissions,
-    id::{marker::GuildMarker, Id},
+    id::{
+        marker::{GuildMarker, UserMarker},
+        Id,
+    },
 };
-use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+use twilight_util::builder::command::{CommandBuilder, StringBuilder, SubCommandBuilder};
 
-use tulpje_framework::{handler_func, Error, Module, ModuleBuilder, Registry};
+use tulpje_framework::{
+    guild_modules::{GuildModuleLookup, ModuleStates},
+    handler_func, Error, Module, ModuleBuilder, Precondition, Registry,
+};
 
 use crate::{
-    context::{CommandContext, Services},
+    context::{AutocompleteContext, CommandContext, Services},
     db::DbId,
 };
 
+pub(crate) mod module_config;
+
 pub(crate) fn build(registry: &Registry<Services>) -> Module<Services> {
-    let guild_module_choices: Vec<(String, String)> = registry
-        .guild_module_names()
+    let config_module_choices: Vec<(String, String)> = registry
+        .configurable_module_names()
         .into_iter()
         .map(|m| (m.clone(), m))
         .collect();
@@ -33,7 +41,7 @@ pub(crate) fn build(registry: &Registry<Services>) -> Module<Services> {
             .dm_permission(false)
             .option(
                 StringBuilder::new("module", "The module to enable")
-                    .choices(guild_module_choices.clone())
+                    .autocomplete(true)
                     .required(true)
                     .build(),
             )
@@ -50,7 +58,7 @@ pub(crate) fn build(registry: &Registry<Services>) -> Module<Services> {
             .dm_permission(false)
             .option(
                 StringBuilder::new("module", "The module to disable")
-                    .choices(guild_module_choices)
+                    .autocomplete(true)
                     .required(true)
                     .build(),
             )
@@ -68,21 +76,155 @@ pub(crate) fn build(registry: &Registry<Services>) -> Module<Services> {
             .build(),
             handler_func!(modules),
         )
+        .preconditions(
+            "enable",
+            [
+                Precondition::GuildOnly,
+                Precondition::MemberPermissions(Permissions::MANAGE_GUILD),
+            ],
+        )
+        .preconditions(
+            "disable",
+            [
+                Precondition::GuildOnly,
+                Precondition::MemberPermissions(Permissions::MANAGE_GUILD),
+            ],
+        )
+        .preconditions(
+            "modules",
+            [
+                Precondition::GuildOnly,
+                Precondition::MemberPermissions(Permissions::MANAGE_GUILD),
+            ],
+        )
+        .command_tree(
+            CommandBuilder::new(
+                "config",
+                "view and change module settings for this server",
+                CommandType::ChatInput,
+            )
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .dm_permission(false)
+            .option(
+                SubCommandBuilder::new("get", "show the value of a setting")
+                    .option(config_module_option(&config_module_choices))
+                    .option(config_key_option())
+                    .build(),
+            )
+            .option(
+                SubCommandBuilder::new("set", "change a setting")
+                    .option(config_module_option(&config_module_choices))
+                    .option(config_key_option())
+                    .option(
+                        StringBuilder::new("value", "The new value")
+                            .required(true)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .option(
+                SubCommandBuilder::new("reset", "reset a setting to its default")
+                    .option(config_module_option(&config_module_choices))
+                    .option(config_key_option())
+                    .build(),
+            )
+            .build(),
+        )
+        .subcommand("config get", handler_func!(module_config::get_setting))
+        .subcommand("config set", handler_func!(module_config::set_setting))
+        .subcommand("config reset", handler_func!(module_config::reset_setting))
+        .preconditions(
+            "config",
+            [
+                Precondition::GuildOnly,
+                Precondition::MemberPermissions(Permissions::MANAGE_GUILD),
+            ],
+        )
+        .autocomplete("enable", "module", handler_func!(autocomplete_enable))
+        .autocomplete("disable", "module", handler_func!(autocomplete_disable))
+        .autocomplete(
+            "config get",
+            "key",
+            handler_func!(module_config::autocomplete_key),
+        )
+        .autocomplete(
+            "config set",
+            "key",
+            handler_func!(module_config::autocomplete_key),
+        )
+        .autocomplete(
+            "config reset",
+            "key",
+            handler_func!(module_config::autocomplete_key),
+        )
+        .build()
+}
+
+fn config_module_option(choices: &[(String, String)]) -> CommandOption {
+    StringBuilder::new("module", "The module to configure")
+        .choices(choices.to_vec())
+        .required(true)
         .build()
 }
 
+fn config_key_option() -> CommandOption {
+    StringBuilder::new("key", "The setting")
+        .autocomplete(true)
+        .required(true)
+        .build()
+}
+
+// suggest modules that aren't enabled in this guild yet
+pub(crate) async fn autocomplete_enable(ctx: AutocompleteContext) -> Result<(), Error> {
+    let guild_id = ctx.event.guild_id.ok_or("command is guild_only")?;
+    let enabled = enabled_modules(&ctx.services.registry, guild_id).await?;
+
+    ctx.autocomplete_strings(
+        ctx.services
+            .registry
+            .toggleable_module_names()
+            .into_iter()
+            .filter(|m| !enabled.contains(m) && m.contains(&ctx.focused_value))
+            .map(|m| (m.clone(), m)),
+    )
+    .await?;
+
+    Ok(())
+}
+
+// suggest modules that are enabled in this guild
+pub(crate) async fn autocomplete_disable(ctx: AutocompleteContext) -> Result<(), Error> {
+    let guild_id = ctx.event.guild_id.ok_or("command is guild_only")?;
+
+    ctx.autocomplete_strings(
+        enabled_modules(&ctx.services.registry, guild_id)
+            .await?
+            .into_iter()
+            .filter(|m| m.contains(&ctx.focused_value))
+            .map(|m| (m.clone(), m)),
+    )
+    .await?;
+
+    Ok(())
+}
+
 pub(crate) async fn enable(ctx: CommandContext) -> Result<(), Error> {
-    let Some(guild) = ctx.guild().await? else {
-        unreachable!("command is guild_only");
-    };
+    let guild = ctx.guild().await?.ok_or("command is guild_only")?;
 
     let module = ctx.get_arg_string("module")?;
-    if !ctx.services.registry.guild_module_names().contains(&module) {
+    if !ctx
+        .services
+        .registry
+        .toggleable_module_names()
+        .contains(&module)
+    {
         ctx.reply(format!("invalid module {}", module)).await?;
         return Ok(());
     }
 
-    db_enable_module(&ctx.services.db, guild.id, &module).await?;
+    let user_id = ctx.event.author_id().ok_or("no author?")?;
+    db_enable_module(&ctx.services.db, guild.id, &module, user_id).await?;
+    ctx.services.registry.invalidate_guild_modules(guild.id);
     set_guild_commands_for_guild(
         &db_guild_modules(&ctx.services.db, guild.id).await?,
         guild.id,
@@ -97,17 +239,21 @@ pub(crate) async fn enable(ctx: CommandContext) -> Result<(), Error> {
 }
 
 pub(crate) async fn disable(ctx: CommandContext) -> Result<(), Error> {
-    let Some(guild) = ctx.guild().await? else {
-        unreachable!("command is guild_only");
-    };
+    let guild = ctx.guild().await?.ok_or("command is guild_only")?;
 
     let module = ctx.get_arg_string("module")?;
-    if !ctx.services.registry.guild_module_names().contains(&module) {
+    if !ctx
+        .services
+        .registry
+        .toggleable_module_names()
+        .contains(&module)
+    {
         ctx.reply(format!("invalid module {}", module)).await?;
         return Ok(());
     }
 
     db_disable_module(&ctx.services.db, guild.id, &module).await?;
+    ctx.services.registry.invalidate_guild_modules(guild.id);
     set_guild_commands_for_guild(
         &db_guild_modules(&ctx.services.db, guild.id).await?,
         guild.id,
@@ -122,15 +268,13 @@ pub(crate) async fn disable(ctx: CommandContext) -> Result<(), Error> {
 }
 
 pub(crate) async fn modules(ctx: CommandContext) -> Result<(), Error> {
-    let Some(guild) = ctx.guild().await? else {
-        unreachable!("command is guild_only");
-    };
+    let guild = ctx.guild().await?.ok_or("command is guild_only")?;
 
-    let modules = db_guild_modules(&ctx.services.db, guild.id).await?;
+    let modules = enabled_modules(&ctx.services.registry, guild.id).await?;
     let available: Vec<String> = ctx
         .services
         .registry
-        .guild_module_names()
+        .toggleable_module_names()
         .into_iter()
         .filter(|m| !modules.contains(m))
         .collect();
@@ -145,14 +289,52 @@ pub(crate) async fn modules(ctx: CommandContext) -> Result<(), Error> {
     Ok(())
 }
 
+// looks up enabled guild modules in the database for the framework
+pub(crate) struct DbGuildModules {
+    db: sqlx::PgPool,
+}
+
+impl DbGuildModules {
+    pub(crate) fn new(db: sqlx::PgPool) -> Self {
+        Self { db }
+    }
+}
+
+impl GuildModuleLookup for DbGuildModules {
+    fn module_states(
+        &self,
+        guild_id: Id<GuildMarker>,
+    ) -> Pin<Box<dyn Future<Output = Result<ModuleStates, Error>> + Send + '_>> {
+        Box::pin(db_module_states(&self.db, guild_id))
+    }
+}
+
+// toggleable modules that are currently enabled for the guild
+async fn enabled_modules(
+    registry: &Registry<Services>,
+    guild_id: Id<GuildMarker>,
+) -> Result<Vec<String>, Error> {
+    let mut enabled = Vec::new();
+    for module in registry.toggleable_module_names() {
+        if registry.module_enabled(guild_id, &module).await? {
+            enabled.push(module);
+        }
+    }
+
+    Ok(enabled)
+}
+
 pub(crate) async fn set_guild_commands_for_guild(
     modules: &[String],
     guild_id: Id<GuildMarker>,
     interaction: InteractionClient<'_>,
     registry: &Registry<Services>,
 ) -> Result<(), Error> {
+    // global modules can be toggled too, but their commands are registered globally
+    let guild_modules = registry.guild_module_names();
     let commands: Vec<Command> = modules
         .iter()
+        .filter(|module| guild_modules.contains(module))
         .filter_map(|module| registry.module_commands(module))
         .flatten()
         .collect();
@@ -176,11 +358,13 @@ async fn db_enable_module(
     db: &sqlx::PgPool,
     guild_id: Id<GuildMarker>,
     module: &String,
+    user_id: Id<UserMarker>,
 ) -> Result<(), Error> {
     sqlx::query!(
-        "INSERT INTO guild_modules (guild_id, module) VALUES ($1, $2) ON CONFLICT (guild_id) DO NOTHING",
+        "INSERT INTO guild_modules (guild_id, module, enabled, enabled_at, enabled_by) VALUES ($1, $2, TRUE, NOW(), $3) ON CONFLICT (guild_id, module) DO UPDATE SET enabled = TRUE, enabled_at = NOW(), enabled_by = $3",
         i64::from(DbId(guild_id)),
         module,
+        i64::from(DbId(user_id)),
     )
     .execute(db)
     .await?;
@@ -188,13 +372,14 @@ async fn db_enable_module(
     Ok(())
 }
 
+// keeps the row around so a disabled global module stays disabled
 async fn db_disable_module(
     db: &sqlx::PgPool,
     guild_id: Id<GuildMarker>,
     module: &String,
 ) -> Result<(), Error> {
     sqlx::query!(
-        "DELETE FROM guild_modules WHERE guild_id = $1 AND module = $2",
+        "INSERT INTO guild_modules (guild_id, module, enabled) VALUES ($1, $2, FALSE) ON CONFLICT (guild_id, module) DO UPDATE SET enabled = FALSE",
         i64::from(DbId(guild_id)),
         module,
     )
@@ -204,12 +389,27 @@ async fn db_disable_module(
     Ok(())
 }
 
+async fn db_module_states(
+    db: &sqlx::PgPool,
+    guild_id: Id<GuildMarker>,
+) -> Result<ModuleStates, Error> {
+    Ok(sqlx::query!(
+        "SELECT module, enabled FROM guild_modules WHERE guild_id = $1",
+        i64::from(DbId(guild_id))
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| (row.module, row.enabled))
+    .collect())
+}
+
 async fn db_guild_modules(
     db: &sqlx::PgPool,
     guild_id: Id<GuildMarker>,
 ) -> Result<Vec<String>, Error> {
     Ok(sqlx::query_scalar!(
-        "SELECT module FROM guild_modules WHERE guild_id = $1",
+        "SELECT module FROM guild_modules WHERE guild_id = $1 AND enabled",
         i64::from(DbId(guild_id))
     )
     .fetch_all(db)
@@ -221,7 +421,7 @@ pub(crate) async fn db_guilds_with_module(
     module: &str,
 ) -> Result<Vec<Id<GuildMarker>>, Error> {
     Ok(sqlx::query_scalar!(
-        "SELECT guild_id FROM guild_modules WHERE module = $1",
+        "SELECT guild_id FROM guild_modules WHERE module = $1 AND enabled",
         module
     )
     .fetch_all(db)
@@ -234,7 +434,7 @@ pub(crate) async fn db_guilds_with_module(
 pub(crate) async fn db_all_guild_modules(
     db: &sqlx::PgPool,
 ) -> Result<HashMap<Id<GuildMarker>, Vec<String>>, Error> {
-    let rows = sqlx::query!("SELECT guild_id, module FROM guild_modules")
+    let rows = sqlx::query!("SELECT guild_id, module FROM guild_modules WHERE enabled")
         .fetch_all(db)
         .await?;
 
@@ -0,0 +1,261 @@
+use bb8_redis::redis::AsyncCommands as _;
+use serde_json::{Map, Value};
+use twilight_model::id::{marker::GuildMarker, Id};
+
+use tulpje_framework::{
+    command_options,
+    module_config::{self, ConfigSchema, ModuleConfig},
+    Error,
+};
+
+use crate::{
+    context::{AutocompleteContext, CommandContext, Services},
+    db::DbId,
+};
+
+// how long module settings stay cached in redis, in seconds
+const CACHE_TTL: u64 = 60 * 60;
+
+command_options! {
+    struct ConfigOptions {
+        module: String,
+        key: String,
+    }
+}
+
+// settings of a module for a guild, using the defaults for anything that
+// hasn't been set
+pub(crate) async fn get<C: ModuleConfig>(
+    services: &Services,
+    guild_id: Id<GuildMarker>,
+    module: &str,
+) -> Result<C, Error> {
+    module_config::parse(get_settings(services, guild_id, module).await?)
+}
+
+pub(crate) async fn get_setting(ctx: CommandContext) -> Result<(), Error> {
+    let guild_id = ctx.event.guild_id.ok_or("command is guild_only")?;
+    let options = ctx.parse_options::<ConfigOptions>()?;
+    let Some(schema) = find_schema(&ctx, guild_id, &options).await? else {
+        return Ok(());
+    };
+
+    let settings = get_settings(&ctx.services, guild_id, &options.module).await?;
+    let value = settings.get(&options.key);
+    let default = if value.is_none() { " (default)" } else { "" };
+    let value = value
+        .or_else(|| schema.defaults.get(&options.key))
+        .unwrap_or(&Value::Null);
+
+    ctx.reply(format!(
+        "`{}.{}` is set to `{}`{}",
+        options.module, options.key, value, default
+    ))
+    .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn set_setting(ctx: CommandContext) -> Result<(), Error> {
+    let guild_id = ctx.event.guild_id.ok_or("command is guild_only")?;
+    let options = ctx.parse_options::<ConfigOptions>()?;
+    let Some(schema) = find_schema(&ctx, guild_id, &options).await? else {
+        return Ok(());
+    };
+
+    let value = match schema.parse_value(&options.key, &ctx.get_arg_string("value")?) {
+        Ok(value) => value,
+        Err(err) => {
+            ctx.reply_ephemeral(format!("error: {}", err)).await?;
+            return Ok(());
+        }
+    };
+
+    let mut settings = get_settings(&ctx.services, guild_id, &options.module).await?;
+    settings
+        .as_object_mut()
+        .ok_or("module settings aren't an object")?
+        .insert(options.key.clone(), value.clone());
+
+    if let Err(err) = schema.validate(&settings) {
+        ctx.reply_ephemeral(format!("error: invalid value for {}: {}", options.key, err))
+            .await?;
+        return Ok(());
+    }
+
+    save_settings(&ctx.services, guild_id, &options.module, &settings).await?;
+
+    ctx.reply(format!(
+        "`{}.{}` set to `{}`",
+        options.module, options.key, value
+    ))
+    .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn reset_setting(ctx: CommandContext) -> Result<(), Error> {
+    let guild_id = ctx.event.guild_id.ok_or("command is guild_only")?;
+    let options = ctx.parse_options::<ConfigOptions>()?;
+    let Some(schema) = find_schema(&ctx, guild_id, &options).await? else {
+        return Ok(());
+    };
+
+    let mut settings = get_settings(&ctx.services, guild_id, &options.module).await?;
+    settings
+        .as_object_mut()
+        .ok_or("module settings aren't an object")?
+        .remove(&options.key);
+
+    save_settings(&ctx.services, guild_id, &options.module, &settings).await?;
+
+    ctx.reply(format!(
+        "`{}.{}` reset to `{}`",
+        options.module,
+        options.key,
+        schema.defaults.get(&options.key).unwrap_or(&Value::Null)
+    ))
+    .await?;
+
+    Ok(())
+}
+
+// suggest the settings of the module that was picked
+pub(crate) async fn autocomplete_key(ctx: AutocompleteContext) -> Result<(), Error> {
+    let module = ctx.option_resolver().get_optional::<String>("module")?;
+    let schema = module
+        .as_deref()
+        .and_then(|module| ctx.services.registry.module_config(module));
+
+    ctx.autocomplete_strings(
+        schema
+            .iter()
+            .flat_map(|schema| schema.settings.iter())
+            .filter(|setting| setting.key.contains(&ctx.focused_value))
+            .map(|setting| {
+                (
+                    format!("{} - {}", setting.key, setting.description),
+                    setting.key,
+                )
+            }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+// replies with an error if the module or setting doesn't exist, or the module
+// isn't enabled for the guild
+async fn find_schema<'a>(
+    ctx: &'a CommandContext,
+    guild_id: Id<GuildMarker>,
+    options: &ConfigOptions,
+) -> Result<Option<&'a ConfigSchema>, Error> {
+    let Some(schema) = ctx.services.registry.module_config(&options.module) else {
+        ctx.reply_ephemeral(format!("error: module {} has no settings", options.module))
+            .await?;
+        return Ok(None);
+    };
+
+    if schema.setting(&options.key).is_none() {
+        ctx.reply_ephemeral(format!(
+            "error: module {} has no setting {}",
+            options.module, options.key
+        ))
+        .await?;
+        return Ok(None);
+    }
+
+    if !ctx
+        .services
+        .registry
+        .module_enabled(guild_id, &options.module)
+        .await?
+    {
+        ctx.reply_ephemeral(format!(
+            "error: the {} module isn't enabled in this server",
+            options.module
+        ))
+        .await?;
+        return Ok(None);
+    }
+
+    Ok(Some(schema))
+}
+
+fn cache_key(guild_id: Id<GuildMarker>, module: &str) -> String {
+    format!("tulpje:module_config:{}:{}", guild_id, module)
+}
+
+async fn get_settings(
+    services: &Services,
+    guild_id: Id<GuildMarker>,
+    module: &str,
+) -> Result<Value, Error> {
+    let key = cache_key(guild_id, module);
+    let mut redis = services.redis.get().await?;
+
+    if let Some(json) = redis.get::<&str, Option<String>>(&key).await? {
+        return Ok(serde_json::from_str(&json)?);
+    }
+
+    let settings = db_module_settings(&services.db, guild_id, module)
+        .await?
+        .unwrap_or_else(|| Value::Object(Map::new()));
+    redis
+        .set_ex::<&str, String, ()>(&key, settings.to_string(), CACHE_TTL)
+        .await?;
+
+    Ok(settings)
+}
+
+async fn save_settings(
+    services: &Services,
+    guild_id: Id<GuildMarker>,
+    module: &str,
+    settings: &Value,
+) -> Result<(), Error> {
+    db_save_module_settings(&services.db, guild_id, module, settings).await?;
+
+    services
+        .redis
+        .get()
+        .await?
+        .del::<String, ()>(cache_key(guild_id, module))
+        .await?;
+
+    Ok(())
+}
+
+async fn db_module_settings(
+    db: &sqlx::PgPool,
+    guild_id: Id<GuildMarker>,
+    module: &str,
+) -> Result<Option<Value>, Error> {
+    Ok(sqlx::query_scalar!(
+        "SELECT settings FROM guild_modules WHERE guild_id = $1 AND module = $2",
+        i64::from(DbId(guild_id)),
+        module,
+    )
+    .fetch_optional(db)
+    .await?)
+}
+
+// global modules don't need a row to be enabled, so this might create one
+async fn db_save_module_settings(
+    db: &sqlx::PgPool,
+    guild_id: Id<GuildMarker>,
+    module: &str,
+    settings: &Value,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO guild_modules (guild_id, module, settings) VALUES ($1, $2, $3) ON CONFLICT (guild_id, module) DO UPDATE SET settings = $3",
+        i64::from(DbId(guild_id)),
+        module,
+        settings,
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
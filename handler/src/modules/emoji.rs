@@ -2,18 +2,21 @@ pub mod clone;
 pub mod commands;
 pub mod db;
 pub mod event_handlers;
+pub mod message_cache;
 pub mod shared;
 
 use twilight_gateway::EventType;
 use twilight_model::{application::command::CommandType, guild::Permissions};
 use twilight_util::builder::command::{CommandBuilder, StringBuilder};
 
-use tulpje_framework::{handler_func, Module, ModuleBuilder};
+use tulpje_framework::{handler_func, Module, ModuleBuilder, Precondition};
 
 use crate::context::Services;
 
 pub(crate) fn build() -> Module<Services> {
     ModuleBuilder::<Services>::new("emoji")
+        .toggleable()
+        .config::<shared::EmojiConfig>()
         // commands
         .command(
             CommandBuilder::new(
@@ -47,6 +50,7 @@ pub(crate) fn build() -> Module<Services> {
             .option(
                 StringBuilder::new("emoji", "emojis to clone")
                     .required(true)
+                    .autocomplete(true)
                     .build(),
             )
             .option(
@@ -63,11 +67,36 @@ pub(crate) fn build() -> Module<Services> {
                 .build(),
             handler_func!(clone::context_command),
         )
+        .preconditions("emoji-stats", [Precondition::GuildOnly])
+        .preconditions(
+            "emoji-clone",
+            [
+                Precondition::GuildOnly,
+                Precondition::BotPermissions(Permissions::MANAGE_GUILD_EXPRESSIONS),
+            ],
+        )
+        .preconditions(
+            "Clone Emojis",
+            [
+                Precondition::GuildOnly,
+                Precondition::BotPermissions(Permissions::MANAGE_GUILD_EXPRESSIONS),
+            ],
+        )
+        // autocomplete
+        .autocomplete(
+            "emoji-clone",
+            "emoji",
+            handler_func!(clone::autocomplete_emoji),
+        )
         // component interactions
         .component(
             "emoji_stats_sort",
             handler_func!(commands::handle_emoji_stats_sort),
         )
+        .component(
+            "emoji_stats",
+            handler_func!(commands::handle_emoji_stats_page),
+        )
         // event handlers
         .event(
             EventType::MessageCreate,
@@ -77,6 +106,14 @@ pub(crate) fn build() -> Module<Services> {
             EventType::MessageUpdate,
             handler_func!(event_handlers::message_update),
         )
+        .event(
+            EventType::MessageDelete,
+            handler_func!(event_handlers::message_delete),
+        )
+        .event(
+            EventType::MessageDeleteBulk,
+            handler_func!(event_handlers::message_delete),
+        )
         .event(
             EventType::ReactionAdd,
             handler_func!(event_handlers::reaction_add),
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use base64::{prelude::BASE64_STANDARD, Engine as _};
 use futures_util::StreamExt as _;
 use twilight_http::Client;
@@ -6,15 +8,13 @@ use tulpje_framework::Error;
 use twilight_model::id::marker::{EmojiMarker, GuildMarker};
 use twilight_model::id::Id;
 
-use crate::context::CommandContext;
+use crate::context::{AutocompleteContext, CommandContext};
 use crate::modules::emoji::db::Emoji;
 use crate::modules::emoji::shared::parse_emojis_from_string;
 
 // requires CREATE_GUILD_EXPRESSIONS permission
 pub(crate) async fn command(ctx: CommandContext) -> Result<(), Error> {
-    let Some(guild) = ctx.guild().await? else {
-        unreachable!("command is guild_only");
-    };
+    let guild = ctx.guild().await?.ok_or("command is guild_only")?;
 
     let emojis = parse_emojis_from_string(
         Id::<GuildMarker>::new(1), /* DUMMY */
@@ -67,9 +67,7 @@ pub(crate) async fn command(ctx: CommandContext) -> Result<(), Error> {
 
 // requires CREATE_GUILD_EXPRESSIONS permission
 pub(crate) async fn context_command(ctx: CommandContext) -> Result<(), Error> {
-    let Some(guild) = ctx.guild().await? else {
-        unreachable!("command is guild_only");
-    };
+    let guild = ctx.guild().await?.ok_or("command is guild_only")?;
 
     let Some(resolved) = &ctx.command.resolved else {
         return Err("no resolved data for context command".into());
@@ -102,6 +100,47 @@ pub(crate) async fn context_command(ctx: CommandContext) -> Result<(), Error> {
     Ok(())
 }
 
+// how many messages back we look for emojis to suggest
+const AUTOCOMPLETE_MESSAGE_LIMIT: u16 = 50;
+
+pub(crate) async fn autocomplete_emoji(ctx: AutocompleteContext) -> Result<(), Error> {
+    let Some(channel) = &ctx.event.channel else {
+        return Err("no channel for autocomplete interaction".into());
+    };
+
+    let messages = ctx
+        .client
+        .channel_messages(channel.id)
+        .limit(AUTOCOMPLETE_MESSAGE_LIMIT)
+        .await?
+        .models()
+        .await?;
+
+    // everything before the last space has already been entered, we only
+    // complete the emoji that's currently being typed
+    let (entered, partial) = match ctx.focused_value.rsplit_once(' ') {
+        Some((entered, partial)) => (format!("{} ", entered), partial.to_lowercase()),
+        None => (String::new(), ctx.focused_value.to_lowercase()),
+    };
+
+    let mut seen = HashSet::new();
+    let choices: Vec<(String, String)> = messages
+        .iter()
+        .flat_map(|message| {
+            parse_emojis_from_string(Id::<GuildMarker>::new(1) /* DUMMY */, &message.content)
+        })
+        .filter(|emoji| seen.insert(emoji.clone()))
+        .filter(|emoji| emoji.name.to_lowercase().contains(&partial))
+        .map(|emoji| (emoji.name.clone(), format!("{}{}", entered, emoji)))
+        // choice values can't be longer than 100 characters
+        .filter(|(_, value)| value.len() <= 100)
+        .collect();
+
+    ctx.autocomplete_strings(choices).await?;
+
+    Ok(())
+}
+
 async fn download_emoji(id: Id<EmojiMarker>, animated: bool) -> Result<String, reqwest::Error> {
     reqwest::get(format!(
         "https://cdn.discordapp.com/emojis/{}.{}",
@@ -1,22 +1,33 @@
 use twilight_model::{
-    application::interaction::application_command::CommandOptionValue,
     channel::message::{
-        component::{ActionRow, SelectMenu, SelectMenuType},
-        Embed,
+        component::{ActionRow, Button, ButtonStyle, SelectMenu, SelectMenuType},
+        Component, Embed,
     },
     guild::Guild,
     http::interaction::{InteractionResponse, InteractionResponseType},
 };
-use twilight_util::builder::{embed::EmbedBuilder, InteractionResponseDataBuilder};
+use twilight_util::builder::{
+    embed::{EmbedBuilder, EmbedFooterBuilder},
+    InteractionResponseDataBuilder,
+};
 
-use tulpje_framework::Error;
+use tulpje_framework::{command_options, custom_id::ComponentState as _, Error};
 
 use super::db;
 use crate::{
     context::{CommandContext, ComponentInteractionContext},
-    modules::emoji::shared::StatsSort,
+    modules::emoji::shared::{StatsSort, StatsState},
 };
 
+// amount of emojis shown per page
+const STATS_PAGE_SIZE: usize = 20;
+
+command_options! {
+    struct StatsOptions {
+        sort: Option<String>,
+    }
+}
+
 fn create_emoji_stats_sort_menu() -> SelectMenu {
     SelectMenu {
         custom_id: "emoji_stats_sort".into(),
@@ -38,33 +49,120 @@ fn create_emoji_stats_sort_menu() -> SelectMenu {
     }
 }
 
+fn create_emoji_stats_page_button(
+    label: &str,
+    state: &StatsState,
+    disabled: bool,
+) -> Result<Button, Error> {
+    Ok(Button {
+        custom_id: Some(state.to_custom_id("emoji_stats")?),
+        label: Some(label.into()),
+        style: ButtonStyle::Secondary,
+        disabled,
+
+        // defaults
+        emoji: None,
+        url: None,
+        sku_id: None,
+    })
+}
+
+fn create_emoji_stats_components(
+    state: &StatsState,
+    page_count: usize,
+) -> Result<Vec<Component>, Error> {
+    let previous = StatsState {
+        sort: state.sort,
+        page: state.page.saturating_sub(1),
+    };
+    let next = StatsState {
+        sort: state.sort,
+        page: state.page + 1,
+    };
+
+    Ok(vec![
+        ActionRow {
+            components: vec![create_emoji_stats_sort_menu().into()],
+        }
+        .into(),
+        ActionRow {
+            components: vec![
+                create_emoji_stats_page_button("Previous", &previous, state.page == 0)?.into(),
+                create_emoji_stats_page_button("Next", &next, next.page >= page_count)?.into(),
+            ],
+        }
+        .into(),
+    ])
+}
+
 async fn create_emoji_stats_embed(
     db: &sqlx::PgPool,
     guild: &Guild,
-    sort: &StatsSort,
-) -> Result<Embed, Error> {
-    let emoji_stats = db::get_emoji_stats(db, guild.id, sort).await?;
-    let emoji_str = if !emoji_stats.is_empty() {
-        emoji_stats
-            .into_iter()
-            .map(|emoji_stats| {
-                format!(
-                    "{} • Used {} times • Last used <t:{}:R>",
-                    emoji_stats.emoji,
-                    emoji_stats.times_used,
-                    emoji_stats.last_used_at.and_utc().timestamp(),
-                )
-            })
-            .collect::<Vec<String>>()
-            .join("\n")
+    state: &StatsState,
+) -> Result<(Embed, usize), Error> {
+    let emoji_stats = db::get_emoji_stats(db, guild.id, &state.sort).await?;
+    let page_count = emoji_stats.len().div_ceil(STATS_PAGE_SIZE).max(1);
+
+    let emoji_str = emoji_stats
+        .into_iter()
+        .skip(state.page * STATS_PAGE_SIZE)
+        .take(STATS_PAGE_SIZE)
+        .map(|emoji_stats| {
+            format!(
+                "{} • Used {} times • Last used <t:{}:R>",
+                emoji_stats.emoji,
+                emoji_stats.times_used,
+                emoji_stats.last_used_at.and_utc().timestamp(),
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let emoji_str = if !emoji_str.is_empty() {
+        emoji_str
     } else {
         "No Data".to_string()
     };
 
-    Ok(EmbedBuilder::new()
-        .title(format!("{} Emotes in {}", sort.name(), guild.name))
-        .description(emoji_str)
-        .build())
+    Ok((
+        EmbedBuilder::new()
+            .title(format!("{} Emotes in {}", state.sort.name(), guild.name))
+            .description(emoji_str)
+            .footer(EmbedFooterBuilder::new(format!(
+                "Page {} of {}",
+                state.page + 1,
+                page_count
+            )))
+            .build(),
+        page_count,
+    ))
+}
+
+async fn update_emoji_stats_message(
+    ctx: &ComponentInteractionContext,
+    state: &StatsState,
+) -> Result<(), Error> {
+    ctx.response(InteractionResponse {
+        kind: InteractionResponseType::DeferredUpdateMessage,
+        data: None,
+    })
+    .await?;
+
+    let guild = ctx.guild().await?.ok_or("outside of guild")?;
+    let (embed, page_count) = create_emoji_stats_embed(&ctx.services.db, &guild, state).await?;
+
+    let components = create_emoji_stats_components(state, page_count)?;
+
+    if let Err(err) = ctx
+        .interaction()
+        .update_response(&ctx.event.token)
+        .embeds(Some(&[embed]))
+        .components(Some(components.as_slice()))
+        .await
+    {
+        tracing::warn!(?err, "failed to update message");
+    }
+
+    Ok(())
 }
 
 pub async fn handle_emoji_stats_sort(ctx: ComponentInteractionContext) -> Result<(), Error> {
@@ -77,12 +175,6 @@ pub async fn handle_emoji_stats_sort(ctx: ComponentInteractionContext) -> Result
     }
     tracing::trace!(interaction = ?ctx.interaction);
 
-    ctx.response(InteractionResponse {
-        kind: InteractionResponseType::DeferredUpdateMessage,
-        data: None,
-    })
-    .await?;
-
     let Some(sort_by) = ctx.interaction.values.first() else {
         return Err("couldn't get selected value".into());
     };
@@ -91,53 +183,38 @@ pub async fn handle_emoji_stats_sort(ctx: ComponentInteractionContext) -> Result
     let sort = StatsSort::try_from_string(sort_by)?;
     tracing::trace!(sort = ?sort);
 
-    let guild = ctx.guild().await?.ok_or("outside of guild")?;
+    // changing the sort order always goes back to the first page
+    update_emoji_stats_message(&ctx, &StatsState { sort, page: 0 }).await
+}
 
-    if let Err(err) = ctx
-        .interaction()
-        .update_response(&ctx.event.token)
-        .embeds(Some(&[create_emoji_stats_embed(
-            &ctx.services.db,
-            &guild,
-            &sort,
-        )
-        .await?]))
-        .components(Some(&[ActionRow {
-            components: vec![create_emoji_stats_sort_menu().into()],
-        }
-        .into()]))
-        .await
-    {
-        tracing::warn!(?err, "failed to update message");
-    }
+pub async fn handle_emoji_stats_page(ctx: ComponentInteractionContext) -> Result<(), Error> {
+    tracing::trace!(interaction = ?ctx.interaction);
 
-    Ok(())
+    let state = ctx.state::<StatsState>()?;
+    tracing::trace!(state = ?state);
+
+    update_emoji_stats_message(&ctx, &state).await
 }
 
 pub async fn cmd_emoji_stats(ctx: CommandContext) -> Result<(), Error> {
     tracing::info!(command_info = ?ctx.command.options);
 
-    let sort = if let Some(option) = ctx.command.options.first() {
-        if let CommandOptionValue::String(str) = &option.value {
-            StatsSort::try_from_string(str)?
-        } else {
-            StatsSort::CountDesc
-        }
-    } else {
-        StatsSort::CountDesc
+    let options = ctx.parse_options::<StatsOptions>()?;
+    let sort = match options.sort {
+        Some(sort) => StatsSort::try_from_string(&sort)?,
+        None => StatsSort::CountDesc,
     };
+    let state = StatsState { sort, page: 0 };
 
     let guild = ctx.guild().await?.ok_or("not in guild")?;
+    let (embed, page_count) = create_emoji_stats_embed(&ctx.services.db, &guild, &state).await?;
 
     let response = InteractionResponse {
         kind: InteractionResponseType::ChannelMessageWithSource,
         data: Some(
             InteractionResponseDataBuilder::new()
-                .embeds([create_emoji_stats_embed(&ctx.services.db, &guild, &sort).await?])
-                .components([ActionRow {
-                    components: vec![create_emoji_stats_sort_menu().into()],
-                }
-                .into()])
+                .embeds([embed])
+                .components(create_emoji_stats_components(&state, page_count)?)
                 .build(),
         ),
     };
@@ -2,7 +2,7 @@ use sqlx::types::chrono;
 
 use tulpje_framework::Error;
 use twilight_model::id::{
-    marker::{EmojiMarker, GuildMarker},
+    marker::{EmojiMarker, GuildMarker, MessageMarker},
     Id,
 };
 
@@ -19,6 +19,7 @@ pub(crate) struct EmojiUse {
     pub(crate) name: String,
     pub(crate) animated: bool,
     pub(crate) created_at: chrono::NaiveDateTime,
+    pub(crate) message_id: Option<DbId<MessageMarker>>,
 }
 
 #[derive(Debug, sqlx::FromRow)]
@@ -91,6 +92,7 @@ pub(crate) async fn save_emoji_use(
     db: &sqlx::PgPool,
     emote: &Emoji,
     timestamp: chrono::DateTime<chrono::Utc>,
+    message_id: Option<Id<MessageMarker>>,
 ) -> Result<(), Error> {
     sqlx::query!(
         "
@@ -99,14 +101,16 @@ pub(crate) async fn save_emoji_use(
                 emoji_id,
                 name,
                 animated,
-                created_at
-            ) VALUES ($1, $2, $3, $4, $5)
+                created_at,
+                message_id
+            ) VALUES ($1, $2, $3, $4, $5, $6)
         ",
         i64::from(emote.guild_id),
         i64::from(emote.id),
         emote.name,
         emote.animated,
         timestamp.naive_utc(),
+        message_id.map(|id| i64::from(DbId(id))),
     )
     .execute(db)
     .await?;
@@ -114,6 +118,23 @@ pub(crate) async fn save_emoji_use(
     Ok(())
 }
 
+// returns how many uses were removed
+pub(crate) async fn delete_message_emoji_uses(
+    db: &sqlx::PgPool,
+    message_ids: &[Id<MessageMarker>],
+) -> Result<u64, Error> {
+    let message_ids: Vec<i64> = message_ids.iter().map(|id| i64::from(DbId(*id))).collect();
+
+    let result = sqlx::query!(
+        "DELETE FROM emoji_uses WHERE message_id = ANY($1)",
+        &message_ids,
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
 pub(crate) async fn get_emoji_stats(
     db: &sqlx::PgPool,
     guild_id: Id<GuildMarker>,
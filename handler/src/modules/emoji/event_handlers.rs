@@ -9,12 +9,17 @@ use twilight_model::{
     id::{marker::EmojiMarker, Id},
 };
 
-use crate::context::EventContext;
+use crate::{context::EventContext, modules::core::module_config};
 
 use tulpje_framework::Error;
 use tulpje_shared::is_pk_proxy;
 
-use super::{db, shared};
+use super::{
+    db,
+    message_cache::{self, CachedMessage},
+    shared,
+    shared::EmojiConfig,
+};
 
 pub async fn handle_message(ctx: EventContext) -> Result<(), Error> {
     let Event::MessageCreate(msg) = &ctx.event else {
@@ -26,6 +31,17 @@ pub async fn handle_message(ctx: EventContext) -> Result<(), Error> {
         return Ok(());
     };
 
+    // cache the message so edits can be diffed against it, PluralKit proxy
+    // messages included so we can recognise their edits
+    let cached = CachedMessage {
+        content: msg.content.clone(),
+        author_id: msg.author.id,
+        application_id: msg.application_id,
+    };
+    if let Err(err) = message_cache::set(&ctx.services, msg.id, &cached).await {
+        error!(err, guild_id = guild_id.get(), "message_cache::set");
+    }
+
     // don't track PluralKit proxy messages
     if is_pk_proxy(&msg.application_id) {
         debug!("skipping PluralKit proxy message");
@@ -38,8 +54,10 @@ pub async fn handle_message(ctx: EventContext) -> Result<(), Error> {
     trace!(message = msg.content, emotes = ?emotes, "message");
 
     for emote in emotes {
-        if shared::is_guild_emoji(&ctx.client, guild_id, *emote.id).await {
-            if let Err(err) = db::save_emoji_use(&ctx.services.db, &emote, timestamp).await {
+        if shared::is_guild_emoji(ctx.cache(), guild_id, *emote.id).await {
+            if let Err(err) =
+                db::save_emoji_use(&ctx.services.db, &emote, timestamp, Some(msg.id)).await
+            {
                 error!(err, guild_id = guild_id.get(), "db::save_emoji_use");
             };
         }
@@ -53,19 +71,6 @@ pub async fn message_update(ctx: EventContext) -> Result<(), Error> {
         unreachable!()
     };
 
-    // TODO: Cache isn't implemented yet so we can't do stuff with message difference
-    // trace!(has_old = ?old_message.is_some(), "message_update");
-    //let Some(old_message) = old_message else {
-    //    return;
-    //};
-
-    // TODO: We can't seem to check application_id here yet, this seems to be fixed in twilight HEAD though
-    // // don't track PluralKit proxy messages
-    // if is_pk_proxy(&evt.application_id) {
-    //     debug!("skipping PluralKit proxy message");
-    //     return;
-    // }
-
     let Some(guild_id) = evt.guild_id else {
         // Don't process non-guild messages
         return Ok(());
@@ -79,12 +84,29 @@ pub async fn message_update(ctx: EventContext) -> Result<(), Error> {
         return Ok(());
     };
 
+    let old_message = match message_cache::get(&ctx.services, evt.id).await {
+        Ok(old_message) => old_message,
+        Err(err) => {
+            error!(err, guild_id = guild_id.get(), "message_cache::get");
+            None
+        }
+    };
+    trace!(has_old = old_message.is_some(), "message_update");
+
+    // don't track PluralKit proxy messages, the update itself doesn't tell us
+    // the application_id so we can only recognise cached ones
+    if old_message
+        .as_ref()
+        .is_some_and(|old_message| is_pk_proxy(&old_message.application_id))
+    {
+        debug!("skipping PluralKit proxy message");
+        return Ok(());
+    }
+
     let guild_emojis: HashSet<Id<EmojiMarker>> = ctx
-        .client
+        .cache()
         .emojis(guild_id)
         .await?
-        .model()
-        .await?
         .into_iter()
         .map(|e| e.id)
         .collect();
@@ -94,10 +116,13 @@ pub async fn message_update(ctx: EventContext) -> Result<(), Error> {
         .and_then(|ts| DateTime::<Utc>::from_timestamp_micros(ts.as_micros()))
         .unwrap_or_else(Utc::now);
 
-    // TODO: Once we implement cache compare the messages
-    //       currently every edit considers every emoji a new one
+    // messages we haven't seen, e.g. ones older than the cache, count every
+    // emoji as a new use
+    let old_content = old_message
+        .as_ref()
+        .map_or("", |old_message| old_message.content.as_str());
     let old_emote_count = shared::count_emojis(
-        shared::parse_emojis_from_string(guild_id, /* &old_message.content */ "")
+        shared::parse_emojis_from_string(guild_id, old_content)
             .into_iter()
             .filter(|e| guild_emojis.contains(&e.id))
             .collect::<Vec<db::Emoji>>(),
@@ -126,7 +151,9 @@ pub async fn message_update(ctx: EventContext) -> Result<(), Error> {
             continue;
         }
 
-        if let Err(err) = db::save_emoji_use(&ctx.services.db, &emote, timestamp).await {
+        if let Err(err) =
+            db::save_emoji_use(&ctx.services.db, &emote, timestamp, Some(evt.id)).await
+        {
             error!(
                 err,
                 guild_id = guild_id.get(),
@@ -135,6 +162,56 @@ pub async fn message_update(ctx: EventContext) -> Result<(), Error> {
             );
         };
     }
+
+    // keep the cache up-to-date for further edits, only once the uses are
+    // saved, so a retried event still sees the old content
+    let author_id = old_message
+        .as_ref()
+        .map(|old_message| old_message.author_id)
+        .or_else(|| evt.author.as_ref().map(|author| author.id));
+    if let Some(author_id) = author_id {
+        let cached = CachedMessage {
+            content: new_content.clone(),
+            author_id,
+            application_id: old_message
+                .as_ref()
+                .and_then(|old_message| old_message.application_id),
+        };
+        if let Err(err) = message_cache::set(&ctx.services, evt.id, &cached).await {
+            error!(err, guild_id = guild_id.get(), "message_cache::set");
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn message_delete(ctx: EventContext) -> Result<(), Error> {
+    let (guild_id, message_ids) = match &ctx.event {
+        Event::MessageDelete(evt) => (evt.guild_id, vec![evt.id]),
+        Event::MessageDeleteBulk(evt) => (evt.guild_id, evt.ids.clone()),
+        _ => unreachable!(),
+    };
+
+    let Some(guild_id) = guild_id else {
+        return Ok(());
+    };
+
+    if let Err(err) = message_cache::delete(&ctx.services, &message_ids).await {
+        error!(err, guild_id = guild_id.get(), "message_cache::delete");
+    }
+
+    let config = module_config::get::<EmojiConfig>(&ctx.services, guild_id, "emoji").await?;
+    if !config.retract_deleted {
+        return Ok(());
+    }
+
+    let retracted = db::delete_message_emoji_uses(&ctx.services.db, &message_ids).await?;
+    debug!(
+        retracted,
+        guild_id = guild_id.get(),
+        "retracted emoji uses of deleted messages"
+    );
+
     Ok(())
 }
 
@@ -151,13 +228,19 @@ pub async fn reaction_add(ctx: EventContext) -> Result<(), Error> {
                 return Ok(());
             };
 
-            if !shared::is_guild_emoji(&ctx.client, guild_id, *id).await {
+            let config =
+                module_config::get::<EmojiConfig>(&ctx.services, guild_id, "emoji").await?;
+            if !config.track_reactions {
+                return Ok(());
+            }
+
+            if !shared::is_guild_emoji(ctx.cache(), guild_id, *id).await {
                 return Ok(());
             }
 
             let emote = db::Emoji::new(*id, guild_id, name.clone(), *animated);
 
-            if let Err(err) = db::save_emoji_use(&ctx.services.db, &emote, now).await {
+            if let Err(err) = db::save_emoji_use(&ctx.services.db, &emote, now, None).await {
                 error!(err, "db::save_emoji_use");
             };
         }
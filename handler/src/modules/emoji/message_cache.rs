@@ -0,0 +1,78 @@
+use bb8_redis::redis::AsyncCommands as _;
+use serde::{Deserialize, Serialize};
+use twilight_model::id::{
+    marker::{ApplicationMarker, MessageMarker, UserMarker},
+    Id,
+};
+
+use tulpje_framework::Error;
+
+use crate::context::Services;
+
+// how long messages stay cached, edits or deletes after this can't be diffed
+// against the old content anymore, in seconds
+const MESSAGE_TTL: u64 = 24 * 60 * 60;
+
+// the parts of a message we need to diff edits, kept separate from the gateway
+// cache because that's already updated by the time we see the edit
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CachedMessage {
+    pub(crate) content: String,
+    pub(crate) author_id: Id<UserMarker>,
+    pub(crate) application_id: Option<Id<ApplicationMarker>>,
+}
+
+fn cache_key(message_id: Id<MessageMarker>) -> String {
+    format!("tulpje:emoji:message:{}", message_id)
+}
+
+pub(crate) async fn get(
+    services: &Services,
+    message_id: Id<MessageMarker>,
+) -> Result<Option<CachedMessage>, Error> {
+    let json = services
+        .redis
+        .get()
+        .await?
+        .get::<String, Option<String>>(cache_key(message_id))
+        .await?;
+
+    Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+}
+
+pub(crate) async fn set(
+    services: &Services,
+    message_id: Id<MessageMarker>,
+    message: &CachedMessage,
+) -> Result<(), Error> {
+    services
+        .redis
+        .get()
+        .await?
+        .set_ex::<String, String, ()>(
+            cache_key(message_id),
+            serde_json::to_string(message)?,
+            MESSAGE_TTL,
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn delete(
+    services: &Services,
+    message_ids: &[Id<MessageMarker>],
+) -> Result<(), Error> {
+    if message_ids.is_empty() {
+        return Ok(());
+    }
+
+    services
+        .redis
+        .get()
+        .await?
+        .del::<Vec<String>, ()>(message_ids.iter().copied().map(cache_key).collect())
+        .await?;
+
+    Ok(())
+}
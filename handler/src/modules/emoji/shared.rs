@@ -1,6 +1,5 @@
 use std::{collections::HashMap, str::FromStr as _};
 
-use twilight_http::Client;
 use twilight_model::{
     channel::message::component::SelectMenuOption,
     id::{
@@ -9,11 +8,50 @@ use twilight_model::{
     },
 };
 
-use tulpje_framework::Error;
+use serde::{Deserialize, Serialize};
+use tulpje_framework::{
+    cache::Cache,
+    custom_id::ComponentState,
+    module_config::{ModuleConfig, Setting, SettingKind},
+    Error,
+};
 
 use super::db;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct EmojiConfig {
+    pub(crate) track_reactions: bool,
+    pub(crate) retract_deleted: bool,
+}
+
+impl Default for EmojiConfig {
+    fn default() -> Self {
+        Self {
+            track_reactions: true,
+            retract_deleted: false,
+        }
+    }
+}
+
+impl ModuleConfig for EmojiConfig {
+    fn settings() -> Vec<Setting> {
+        vec![
+            Setting {
+                key: "track_reactions",
+                description: "count reactions as emoji uses",
+                kind: SettingKind::Boolean,
+            },
+            Setting {
+                key: "retract_deleted",
+                description: "stop counting emoji uses of deleted messages",
+                kind: SettingKind::Boolean,
+            },
+        ]
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub(crate) enum StatsSort {
     CountDesc,
     CountAsc,
@@ -63,6 +101,30 @@ impl From<StatsSort> for SelectMenuOption {
     }
 }
 
+// state of an emoji stats message, stored in the custom_id of the page buttons
+#[derive(Debug, PartialEq)]
+pub(crate) struct StatsState {
+    pub(crate) sort: StatsSort,
+    pub(crate) page: usize,
+}
+
+impl ComponentState for StatsState {
+    fn to_args(&self) -> Vec<String> {
+        vec![self.sort.id().to_string(), self.page.to_string()]
+    }
+
+    fn from_args(args: &[String]) -> Result<Self, Error> {
+        let [sort, page] = args else {
+            return Err(format!("expected 2 arguments, got {}", args.len()).into());
+        };
+
+        Ok(Self {
+            sort: StatsSort::try_from_string(sort)?,
+            page: page.parse()?,
+        })
+    }
+}
+
 pub(crate) fn parse_emojis_from_string(guild_id: Id<GuildMarker>, content: &str) -> Vec<db::Emoji> {
     let re = regex::Regex::new(r"<(a?):([[:word:]]+):([[:digit:]]+)>").unwrap();
     re.captures_iter(content)
@@ -93,14 +155,15 @@ pub(crate) fn count_emojis(emojis: Vec<db::Emoji>) -> HashMap<db::Emoji, i16> {
     counts
 }
 
-// TODO: Check if this is a 404 emoji not found so we can assume
-//       safely it's an emoji in a different guild
 pub(crate) async fn is_guild_emoji(
-    http: &Client,
+    cache: &Cache,
     guild_id: Id<GuildMarker>,
     emoji_id: Id<EmojiMarker>,
 ) -> bool {
-    http.emoji(guild_id, emoji_id).await.is_ok()
+    cache
+        .emojis(guild_id)
+        .await
+        .is_ok_and(|emojis| emojis.iter().any(|emoji| emoji.id == emoji_id))
 }
 
 #[cfg(test)]
@@ -130,6 +193,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn stats_state_test() {
+        let state = StatsState {
+            sort: StatsSort::DateAsc,
+            page: 2,
+        };
+
+        let args = state.to_args();
+        assert_eq!(args, vec![String::from("date_asc"), String::from("2")]);
+        assert_eq!(StatsState::from_args(&args).unwrap(), state);
+        assert!(
+            StatsState::from_args(&[String::from("date_asc")]).is_err(),
+            "missing page should be an error"
+        );
+    }
+
     #[test]
     fn count_emojis_test() {
         // emoji creation helper func
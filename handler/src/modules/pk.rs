@@ -1,7 +1,9 @@
 use twilight_model::{application::command::CommandType, guild::Permissions};
-use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+use twilight_util::builder::command::{
+    CommandBuilder, StringBuilder, SubCommandBuilder, SubCommandGroupBuilder,
+};
 
-use tulpje_framework::{handler_func, Module, ModuleBuilder};
+use tulpje_framework::{handler_func, Module, ModuleBuilder, Precondition};
 
 use crate::context::Services;
 
@@ -15,56 +17,61 @@ pub fn build() -> Module<Services> {
     ModuleBuilder::<Services>::new("pluralkit")
         .guild()
         // commands
-        .command(
-            CommandBuilder::new(
-                "setup-pk",
-                "set-up the PluralKit module",
-                CommandType::ChatInput,
-            )
-            .default_member_permissions(Permissions::MANAGE_GUILD)
-            .dm_permission(false)
-            .option(
-                StringBuilder::new("system_id", "PluralKit system ID")
-                    .required(true)
-                    .build(),
-            )
-            .option(StringBuilder::new("token", "(optional) PluralKit token").build())
-            .build(),
-            handler_func!(commands::setup_pk),
+        .command_tree(
+            CommandBuilder::new("pk", "PluralKit commands", CommandType::ChatInput)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .dm_permission(false)
+                .option(
+                    SubCommandBuilder::new("setup", "set-up the PluralKit module")
+                        .option(
+                            StringBuilder::new("system_id", "PluralKit system ID")
+                                .required(true)
+                                .build(),
+                        )
+                        .option(StringBuilder::new("token", "(optional) PluralKit token").build())
+                        .build(),
+                )
+                .option(
+                    SubCommandGroupBuilder::new("fronters", "fronter channels")
+                        .subcommands([
+                            SubCommandBuilder::new("setup", "set-up fronter channels").option(
+                                StringBuilder::new("name", "Name of the fronters category").build(),
+                            ),
+                            SubCommandBuilder::new("update", "manually update fronter channels"),
+                        ])
+                        .build(),
+                )
+                .option(
+                    SubCommandGroupBuilder::new("roles", "member roles")
+                        .subcommands([SubCommandBuilder::new("update", "update the member roles")])
+                        .build(),
+                )
+                .build(),
         )
-        .command(
-            CommandBuilder::new(
-                "setup-fronters",
-                "set-up fronter channels",
-                CommandType::ChatInput,
-            )
-            .default_member_permissions(Permissions::MANAGE_GUILD)
-            .dm_permission(false)
-            .option(StringBuilder::new("name", "Name of the fronters category").build())
-            .build(),
+        .subcommand("pk setup", handler_func!(commands::setup_pk))
+        .subcommand(
+            "pk fronters setup",
             handler_func!(fronters::commands::setup_fronters),
         )
-        .command(
-            CommandBuilder::new(
-                "update-fronters",
-                "manually update fronter channels",
-                CommandType::ChatInput,
-            )
-            .default_member_permissions(Permissions::MANAGE_GUILD)
-            .dm_permission(false)
-            .build(),
+        .subcommand(
+            "pk fronters update",
             handler_func!(fronters::commands::update_fronters),
         )
-        .command(
-            CommandBuilder::new(
-                "update-member-roles",
-                "update the member roles",
-                CommandType::ChatInput,
-            )
-            .default_member_permissions(Permissions::MANAGE_GUILD)
-            .dm_permission(false)
-            .build(),
-            handler_func!(roles::update_member_roles),
+        .subcommand("pk roles update", handler_func!(roles::update_member_roles))
+        .preconditions(
+            "pk",
+            [
+                Precondition::GuildOnly,
+                Precondition::MemberPermissions(Permissions::MANAGE_GUILD),
+            ],
+        )
+        .preconditions(
+            "pk fronters",
+            [Precondition::BotPermissions(Permissions::MANAGE_CHANNELS)],
+        )
+        .preconditions(
+            "pk roles",
+            [Precondition::BotPermissions(Permissions::MANAGE_ROLES)],
         )
         // tasks
         .task(
@@ -1,28 +1,32 @@
 use pkrs::model::PkId;
 use tracing::debug;
 
-use tulpje_framework::Error;
+use tulpje_framework::{command_options, Error};
 
 use super::db;
 use crate::context::CommandContext;
 
 // TODO: command to see current settings
 
+command_options! {
+    struct SetupOptions {
+        system_id: String,
+        token: Option<String>,
+    }
+}
+
 pub async fn setup_pk(ctx: CommandContext) -> Result<(), Error> {
-    let Some(guild) = ctx.guild().await? else {
-        unreachable!("command is guild_only");
-    };
+    let guild = ctx.guild().await?.ok_or("command is guild_only")?;
 
     ctx.defer_ephemeral().await?;
 
     let user_id = ctx.event.author_id().ok_or("no author?")?;
-    let system_id = ctx.get_arg_string("system_id")?;
-    let token = ctx.get_arg_string_optional("token")?;
+    let SetupOptions { system_id, token } = ctx.parse_options()?;
 
     debug!(
         guild_id = guild.id.get(),
         guild_name = guild.name,
-        command = "setup-pk",
+        command = "pk setup",
         system_id = system_id
     );
 
@@ -191,20 +191,18 @@ pub(crate) async fn update_fronter_channels(
 }
 
 pub(crate) async fn update_fronters(ctx: CommandContext) -> Result<(), Error> {
-    let Some(guild) = ctx.guild().await? else {
-        unreachable!("command is guild_only");
-    };
+    let guild = ctx.guild().await?.ok_or("command is guild_only")?;
 
     ctx.defer_ephemeral().await?;
 
     let Some(cat_id) = db::get_fronter_category(&ctx.services.db, guild.id).await? else {
-        ctx.update("fronter category not set-up, please run /setup-fronters")
+        ctx.update("fronter category not set-up, please run /pk fronters setup")
             .await?;
         return Ok(());
     };
 
     let Some(gs) = get_guild_settings_for_id(&ctx.services.db, guild.id).await? else {
-        ctx.update("PluralKit module not set-up, please run /setup-pk")
+        ctx.update("PluralKit module not set-up, please run /pk setup")
             .await?;
         return Ok(());
     };
@@ -253,9 +251,7 @@ async fn create_or_get_fronter_channel(
 }
 
 pub(crate) async fn setup_fronters(ctx: CommandContext) -> Result<(), Error> {
-    let Some(guild) = ctx.guild().await? else {
-        unreachable!("command is guild_only");
-    };
+    let guild = ctx.guild().await?.ok_or("command is guild_only")?;
 
     ctx.defer_ephemeral().await?;
 
@@ -15,9 +15,13 @@ use twilight_util::builder::{
 };
 
 use tulpje_framework::{handler_func, Error, Module, ModuleBuilder};
-use tulpje_shared::{metrics::Metrics, shard_state::ShardState};
+use tulpje_shared::{
+    metrics::{Metrics, METRICS_KEY},
+    shard_plan,
+    shard_state::{ShardState, SHARD_STATUS_KEY},
+};
 
-use crate::context::{CommandContext, Services};
+use crate::context::{CommandContext, Services, TaskContext};
 
 pub(crate) fn build() -> Module<Services> {
     ModuleBuilder::<Services>::new("stats")
@@ -43,16 +47,22 @@ pub(crate) fn build() -> Module<Services> {
             .build(),
             handler_func!(cmd_processes),
         )
+        .task(
+            "stats:remove-stale",
+            "0 * * * * *", // every minute
+            handler_func!(remove_stale),
+        )
         .build()
 }
 
+// only returns the shards of the shard count that's publishing events
 pub async fn get_all_shard_stats(
     redis: bb8::Pool<RedisConnectionManager>,
 ) -> Result<HashMap<u32, ShardState>, Error> {
-    Ok(redis
+    let states: Vec<ShardState> = redis
         .get()
         .await?
-        .hgetall::<&str, HashMap<String, String>>("tulpje:shard_status")
+        .hgetall::<&str, HashMap<String, String>>(SHARD_STATUS_KEY)
         .await?
         .into_iter()
         .filter_map(
@@ -61,12 +71,52 @@ pub async fn get_all_shard_stats(
                     tracing::warn!("error decoding shard state {}: {}", id, err);
                     None
                 }
-                Ok(state) => Some((state.shard_id, state)),
+                Ok(state) => Some(state),
             },
         )
+        .collect();
+
+    let shard_counts = running_shard_counts(&redis, states.iter()).await?;
+    let Some(shard_count) = shard_counts.first() else {
+        return Ok(HashMap::new());
+    };
+
+    Ok(states
+        .into_iter()
+        .filter(|state| state.shard_count == *shard_count)
+        .map(|state| (state.shard_id, state))
         .collect())
 }
 
+// the shard counts that are running, starting with the one that's publishing
+// events, both generations of shards run while resharding, the shard plan is
+// only used if gateways are following it, otherwise it's the shard count of
+// the most recently updated shard
+async fn running_shard_counts<'a>(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    states: impl Iterator<Item = &'a ShardState> + Clone,
+) -> Result<Vec<u32>, Error> {
+    if let Some(plan) = shard_plan::load(redis).await? {
+        let shard_counts: Vec<u32> = [Some(plan.publishing()), Some(plan.current), plan.next]
+            .into_iter()
+            .flatten()
+            .map(|set| set.shard_count)
+            .collect();
+
+        if states
+            .clone()
+            .any(|state| shard_counts.contains(&state.shard_count))
+        {
+            return Ok(shard_counts);
+        }
+    }
+
+    Ok(states
+        .max_by_key(|state| state.updated_at)
+        .map(|state| vec![state.shard_count])
+        .unwrap_or_default())
+}
+
 pub async fn cmd_stats(ctx: CommandContext) -> Result<(), Error> {
     let time_before = chrono::Utc::now().timestamp_millis();
     ctx.reply("...").await?;
@@ -75,20 +125,22 @@ pub async fn cmd_stats(ctx: CommandContext) -> Result<(), Error> {
 
     let shard_stats = get_all_shard_stats(ctx.services.redis.clone()).await?;
     let total_shards = shard_stats.len();
-    // TODO: Handle dead shards somehow, they don't get cleaned up automatically
     let shards_up = shard_stats.iter().filter(|(_, s)| s.is_up()).count();
     let guild_count: u64 = shard_stats.values().map(|s| s.guild_count).sum();
 
+    let Some(current_shard_state) = shard_stats.get(&ctx.meta.shard) else {
+        return Err(format!("couldn't get current shard state {}", ctx.meta.shard).into());
+    };
+
     let handler_cpu_usage = get_process_stats(
         &ctx.services.redis,
         &format!("handler-{}", ctx.services.handler_id),
     )
     .await?
     .map(|m| m.cpu_usage);
-    let gateway_cpu_usage =
-        get_process_stats(&ctx.services.redis, &format!("gateway-{}", ctx.meta.shard))
-            .await?
-            .map(|m| m.cpu_usage);
+    let gateway_cpu_usage = get_process_stats(&ctx.services.redis, &current_shard_state.process)
+        .await?
+        .map(|m| m.cpu_usage);
 
     let cpu_usage_str = if let (Some(handler_cpu_usage), Some(gateway_cpu_usage)) =
         (handler_cpu_usage, gateway_cpu_usage)
@@ -107,14 +159,9 @@ pub async fn cmd_stats(ctx: CommandContext) -> Result<(), Error> {
     )
     .await?
     .map(|m| m.memory_usage);
-    let gateway_mem_usage =
-        get_process_stats(&ctx.services.redis, &format!("gateway-{}", ctx.meta.shard))
-            .await?
-            .map(|m| m.memory_usage);
-
-    let Some(current_shard_state) = shard_stats.get(&ctx.meta.shard) else {
-        return Err(format!("couldn't get current shard state {}", ctx.meta.shard).into());
-    };
+    let gateway_mem_usage = get_process_stats(&ctx.services.redis, &current_shard_state.process)
+        .await?
+        .map(|m| m.memory_usage);
 
     let mem_usage_str = if let (Some(handler_mem_usage), Some(gateway_mem_usage)) =
         (handler_mem_usage, gateway_mem_usage)
@@ -234,7 +281,7 @@ pub async fn cmd_shards(ctx: CommandContext) -> Result<(), Error> {
                     format!("Shard #{}", shard.shard_id),
                     if shard.is_up() {
                         format!(
-                            "Latency: {} ms / Uptime: {} / Servers: {} / Disconnects: {}",
+                            "Latency: {} ms / Uptime: {} / Servers: {} / Disconnects: {} / Process: {} ({})",
                             shard.latency.to_formatted_string(&Locale::en),
                             tulpje_shared::format_significant_duration(
                                 chrono::DateTime::from_timestamp(
@@ -248,6 +295,8 @@ pub async fn cmd_shards(ctx: CommandContext) -> Result<(), Error> {
                             ),
                             shard.guild_count.to_formatted_string(&Locale::en),
                             shard.disconnect_count.to_formatted_string(&Locale::en),
+                            shard.process,
+                            shard.host,
                         )
                     } else {
                         "Down".into()
@@ -284,7 +333,7 @@ pub async fn get_process_stats(
     let json = redis
         .get()
         .await?
-        .hget::<&str, &str, Option<String>>("tulpje:metrics", name)
+        .hget::<&str, &str, Option<String>>(METRICS_KEY, name)
         .await?;
 
     Ok(match json {
@@ -299,7 +348,7 @@ pub async fn get_all_process_stats(
     Ok(redis
         .get()
         .await?
-        .hgetall::<&str, HashMap<String, String>>("tulpje:metrics")
+        .hgetall::<&str, HashMap<String, String>>(METRICS_KEY)
         .await?
         .into_iter()
         .filter_map(
@@ -333,9 +382,10 @@ pub async fn cmd_processes(ctx: CommandContext) -> Result<(), Error> {
                 EmbedFieldBuilder::new(
                     process.name,
                     format!(
-                        "CPU: {:.2}% / Mem: {:.2}MiB",
+                        "CPU: {:.2}% / Mem: {:.2}MiB / Host: {}",
                         process.cpu_usage,
-                        process.memory_usage as f64 / 1024. / 1024.
+                        process.memory_usage as f64 / 1024. / 1024.,
+                        process.host,
                     ),
                 )
                 .into(),
@@ -361,3 +411,58 @@ pub async fn cmd_processes(ctx: CommandContext) -> Result<(), Error> {
 
     Ok(())
 }
+
+// removes states of shards and processes that are gone, or shards that are
+// beyond the current shard count, so the stats only show what's running
+pub(crate) async fn remove_stale(ctx: TaskContext) -> Result<(), Error> {
+    let redis = &ctx.services.redis;
+    let mut conn = redis.get().await?;
+
+    let shard_states: HashMap<String, Option<ShardState>> = conn
+        .hgetall::<&str, HashMap<String, String>>(SHARD_STATUS_KEY)
+        .await?
+        .into_iter()
+        .map(|(field, json)| (field, serde_json::from_str(&json).ok()))
+        .collect();
+
+    let shard_counts = running_shard_counts(redis, shard_states.values().flatten()).await?;
+
+    let stale_shards: Vec<String> = shard_states
+        .into_iter()
+        .filter(|(_, state)| {
+            // states we can't decode are removed too
+            let Some(state) = state else {
+                return true;
+            };
+
+            state.is_stale(&shard_counts)
+        })
+        .map(|(field, _)| field)
+        .collect();
+    if !stale_shards.is_empty() {
+        tracing::info!(shards = ?stale_shards, "removing stale shard states");
+        conn.hdel::<&str, Vec<String>, ()>(SHARD_STATUS_KEY, stale_shards)
+            .await?;
+    }
+
+    let stale_processes: Vec<String> = conn
+        .hgetall::<&str, HashMap<String, String>>(METRICS_KEY)
+        .await?
+        .into_iter()
+        .filter(|(_, json)| {
+            let Ok(metrics) = serde_json::from_str::<Metrics>(json) else {
+                return true;
+            };
+
+            metrics.is_stale()
+        })
+        .map(|(name, _)| name)
+        .collect();
+    if !stale_processes.is_empty() {
+        tracing::info!(processes = ?stale_processes, "removing stale process metrics");
+        conn.hdel::<&str, Vec<String>, ()>(METRICS_KEY, stale_processes)
+            .await?;
+    }
+
+    Ok(())
+}
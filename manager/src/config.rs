@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_envfile::Error;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Config {
+    pub discord_token: String,
+    pub redis_url: String,
+
+    // the shard plan is only managed if gateways follow it, same as the
+    // gateways' SHARD_PLAN
+    #[serde(default)]
+    pub shard_plan: bool,
+
+    // always run this many shards instead of what discord recommends
+    #[serde(default)]
+    pub shard_count: Option<u32>,
+
+    // how often to check the recommended shard count, in seconds
+    #[serde(default = "default_check_interval")]
+    pub check_interval: u64,
+    // how long the new shards get to become ready before resharding is
+    // aborted, in seconds
+    #[serde(default = "default_start_timeout")]
+    pub start_timeout: u64,
+    // how long the new shards get to take over publishing after cutting over,
+    // in seconds
+    #[serde(default = "default_handover_timeout")]
+    pub handover_timeout: u64,
+}
+
+fn default_check_interval() -> u64 {
+    60 * 60
+}
+
+fn default_start_timeout() -> u64 {
+    15 * 60
+}
+
+fn default_handover_timeout() -> u64 {
+    5 * 60
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, Error> {
+        serde_envfile::from_env()
+    }
+
+    pub fn check_interval(&self) -> Duration {
+        Duration::from_secs(self.check_interval)
+    }
+}
@@ -1,5 +1,22 @@
+use bb8_redis::RedisConnectionManager;
+
+mod config;
+mod orchestrator;
+
+use config::Config;
+use orchestrator::Orchestrator;
+
+fn usage() -> ! {
+    println!("usage: tulpje-manager [command]");
+    println!();
+    println!("commands:");
+    println!("  run          keep the shard plan up-to-date (default)");
+    println!("  recommended  print the recommended shard count");
+    std::process::exit(64);
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<(), orchestrator::Error> {
     // load .env into environment vars, ignore if not found
     match dotenvy::dotenv().map(|_| ()) {
         Err(err) if err.not_found() => eprintln!("warn: no .env file found"),
@@ -7,6 +24,43 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Ok(()) => (),
     };
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => run().await,
+        [command] if command == "run" => run().await,
+        [command] if command == "recommended" => recommended().await,
+        _ => usage(),
+    }
+}
+
+async fn run() -> Result<(), orchestrator::Error> {
+    let config = Config::from_env()?;
+
+    // set-up logging
+    tracing_subscriber::fmt::init();
+
+    // idle instead of exiting, so we don't get restarted over and over
+    if !config.shard_plan {
+        tracing::warn!("SHARD_PLAN isn't enabled, not managing the shard plan");
+        tulpje_shared::shutdown::signal().await;
+        return Ok(());
+    }
+
+    let client = twilight_http::Client::builder()
+        .token(config.discord_token.clone())
+        .build();
+
+    let manager = RedisConnectionManager::new(config.redis_url.clone())?;
+    let redis = bb8::Pool::builder().build(manager).await?;
+
+    tracing::info!("managing shard plan...");
+    Orchestrator::new(config, client, redis).run().await;
+
+    Ok(())
+}
+
+// used when deploying to find out how many gateways to start
+async fn recommended() -> Result<(), orchestrator::Error> {
     let token = match std::env::var("DISCORD_TOKEN") {
         Ok(token) => token,
         Err(std::env::VarError::NotPresent) => {
@@ -20,9 +74,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     let client = twilight_http::Client::builder().token(token).build();
-    let connection_info = client.gateway().authed().await?.model().await?;
-
-    println!("{}", connection_info.shards);
+    println!("{}", orchestrator::recommended_shard_count(&client).await?);
 
     Ok(())
 }
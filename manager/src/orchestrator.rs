@@ -0,0 +1,170 @@
+use std::time::{Duration, Instant};
+
+use bb8_redis::RedisConnectionManager;
+use twilight_http::Client;
+
+use tulpje_shared::shard_plan::{self, Phase, ShardPlan, ShardSet};
+
+use crate::config::Config;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+// how often the plan is checked and moved to its next phase
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+// keeps the shard plan in redis up-to-date, see `tulpje_shared::shard_plan`,
+// gateways follow the plan so all we do here is move it along once the
+// gateways have caught up
+pub struct Orchestrator {
+    config: Config,
+    client: Client,
+    redis: bb8::Pool<RedisConnectionManager>,
+    last_check: Option<Instant>,
+}
+
+impl Orchestrator {
+    pub fn new(config: Config, client: Client, redis: bb8::Pool<RedisConnectionManager>) -> Self {
+        Self {
+            config,
+            client,
+            redis,
+            last_check: None,
+        }
+    }
+
+    pub async fn run(mut self) {
+        loop {
+            if let Err(err) = self.step().await {
+                tracing::error!("error updating shard plan: {}", err);
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn step(&mut self) -> Result<(), Error> {
+        let Some(plan) = shard_plan::load(&self.redis).await? else {
+            let shard_count = self.target_shard_count().await?;
+            tracing::info!(shard_count, "no shard plan yet, creating one");
+
+            return shard_plan::save(&self.redis, &ShardPlan::new(shard_count)).await;
+        };
+
+        match (plan.phase, plan.next) {
+            (Phase::Stable, _) => self.check_shard_count(&plan).await,
+            (Phase::Starting, Some(next)) => self.check_started(&plan, next).await,
+            (Phase::CuttingOver, Some(next)) => self.check_handed_over(&plan, next).await,
+            (_, None) => {
+                tracing::warn!("resharding without a next generation, aborting");
+                shard_plan::save(&self.redis, &plan.abort()).await
+            }
+        }
+    }
+
+    // starts resharding if the shard count should change
+    async fn check_shard_count(&mut self, plan: &ShardPlan) -> Result<(), Error> {
+        if self
+            .last_check
+            .is_some_and(|last_check| last_check.elapsed() < self.config.check_interval())
+        {
+            return Ok(());
+        }
+        self.last_check = Some(Instant::now());
+
+        let current = plan.current.shard_count;
+        let target = self.target_shard_count().await?;
+
+        // discord only requires more shards as we grow, so unless the shard
+        // count is set we never go below what we're running
+        let reshard = if self.config.shard_count.is_some() {
+            target != current
+        } else {
+            target > current
+        };
+        if !reshard {
+            tracing::debug!(shard_count = current, "shard count is up-to-date");
+            return Ok(());
+        }
+
+        tracing::info!(from = current, to = target, "resharding");
+        shard_plan::save(&self.redis, &plan.start_resharding(target)).await
+    }
+
+    // cuts over once every shard of the next generation has been ready
+    async fn check_started(&self, plan: &ShardPlan, next: ShardSet) -> Result<(), Error> {
+        let ready = shard_plan::ready_count(&self.redis, next.generation).await?;
+
+        if ready >= next.shard_count {
+            tracing::info!(
+                generation = next.generation,
+                "all shards ready, cutting over"
+            );
+            return shard_plan::save(&self.redis, &plan.cut_over()).await;
+        }
+
+        if plan.age() > self.config.start_timeout {
+            tracing::warn!(
+                generation = next.generation,
+                ready,
+                shard_count = next.shard_count,
+                "shards didn't become ready in time, aborting resharding"
+            );
+            shard_plan::save(&self.redis, &plan.abort()).await?;
+            return shard_plan::clear_generation(&self.redis, next.generation).await;
+        }
+
+        tracing::debug!(
+            generation = next.generation,
+            ready,
+            shard_count = next.shard_count,
+            "waiting for shards"
+        );
+        Ok(())
+    }
+
+    // finishes once every shard of the next generation is publishing, the
+    // previous generation keeps publishing until then and retires after
+    async fn check_handed_over(&self, plan: &ShardPlan, next: ShardSet) -> Result<(), Error> {
+        let publishing = shard_plan::publishing_count(&self.redis, next.generation).await?;
+
+        if publishing < next.shard_count {
+            if plan.age() <= self.config.handover_timeout {
+                tracing::debug!(
+                    generation = next.generation,
+                    publishing,
+                    shard_count = next.shard_count,
+                    "waiting for shards to take over publishing"
+                );
+                return Ok(());
+            }
+
+            tracing::warn!(
+                generation = next.generation,
+                publishing,
+                shard_count = next.shard_count,
+                "shards didn't take over publishing in time, finishing anyway"
+            );
+        }
+
+        let finished = plan.finish();
+        tracing::info!(
+            generation = finished.current.generation,
+            shard_count = finished.current.shard_count,
+            "resharding finished"
+        );
+        shard_plan::save(&self.redis, &finished).await?;
+        shard_plan::clear_generation(&self.redis, plan.current.generation).await
+    }
+
+    async fn target_shard_count(&self) -> Result<u32, Error> {
+        if let Some(shard_count) = self.config.shard_count {
+            return Ok(shard_count);
+        }
+
+        recommended_shard_count(&self.client).await
+    }
+}
+
+pub async fn recommended_shard_count(client: &Client) -> Result<u32, Error> {
+    Ok(client.gateway().authed().await?.model().await?.shards)
+}
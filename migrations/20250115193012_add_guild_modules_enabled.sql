@@ -0,0 +1,9 @@
+-- rows can now also disable a module for a guild, for global modules that
+-- can be toggled per guild
+ALTER TABLE guild_modules ADD COLUMN enabled BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- guild_id alone was the primary key, so a guild could only have a row for one
+-- module, enabling or disabling another would conflict on it
+ALTER TABLE guild_modules DROP CONSTRAINT guild_modules_pkey;
+ALTER TABLE guild_modules DROP CONSTRAINT guild_modules_guild_id_module_key;
+ALTER TABLE guild_modules ADD PRIMARY KEY (guild_id, module);
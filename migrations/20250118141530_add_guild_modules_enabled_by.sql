@@ -0,0 +1,5 @@
+-- enabled_at/enabled_by are unknown for modules enabled before this migration
+ALTER TABLE guild_modules
+    ADD COLUMN enabled_at TIMESTAMP,
+    ADD COLUMN enabled_by BIGINT,
+    ADD COLUMN settings JSONB NOT NULL DEFAULT '{}';
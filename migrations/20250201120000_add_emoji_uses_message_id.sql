@@ -0,0 +1,4 @@
+-- message the emoji was used in, so uses can be retracted when it's deleted,
+-- NULL for reactions and uses recorded before this was added
+ALTER TABLE emoji_uses ADD COLUMN message_id BIGINT;
+CREATE INDEX emoji_uses_message_id_idx ON emoji_uses (message_id);
@@ -0,0 +1,424 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tokio::{
+    sync::{
+        mpsc::{self, error::TrySendError},
+        RwLock,
+    },
+    task::JoinHandle,
+};
+
+#[cfg(feature = "amqp-amqprs")]
+pub mod amqprs;
+#[cfg(feature = "amqp-lapin")]
+pub mod lapin;
+pub mod memory;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+// only string header values are supported, that's all we use
+pub type Headers = BTreeMap<String, String>;
+
+// routing key, headers and data
+pub type Message = (String, Headers, Vec<u8>);
+
+// how long to wait for rabbitmq to confirm a message before reconnecting
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+
+// most messages `BufferedPublisher` publishes before waiting for confirmations
+const MAX_BATCH: usize = 100;
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExchangeKind {
+    Direct,
+    Fanout,
+    Topic,
+}
+impl ExchangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Direct => "direct",
+            Self::Fanout => "fanout",
+            Self::Topic => "topic",
+        }
+    }
+}
+
+// a single amqp connection, implemented for every backend
+#[async_trait]
+pub trait Connection: Send + Sync + Sized + 'static {
+    async fn connect(addr: &str) -> Result<Self, Error>;
+
+    // exchanges and queues are always declared durable
+    async fn declare_exchange(&self, name: &str, kind: ExchangeKind) -> Result<(), Error>;
+    async fn declare_queue(&self, name: &str) -> Result<(), Error>;
+    async fn bind_queue(&self, queue: &str, exchange: &str, binding_key: &str)
+        -> Result<(), Error>;
+
+    // publishes a message and waits for the broker to confirm it
+    async fn publish(
+        &self,
+        exchange: &str,
+        routing_key: &str,
+        headers: &Headers,
+        data: &[u8],
+    ) -> Result<(), Error>;
+
+    // publishes messages in order and waits for the broker to confirm all of
+    // them, backends that can't wait on several confirmations at once publish
+    // them one by one
+    async fn publish_batch(&self, exchange: &str, messages: &[Message]) -> Result<(), Error> {
+        for (routing_key, headers, data) in messages {
+            self.publish(exchange, routing_key, headers, data).await?;
+        }
+
+        Ok(())
+    }
+
+    // starts consuming a queue with manual acks, the consumer ends once the
+    // connection is lost
+    async fn consume(&self, queue: &str, prefetch: u16) -> Result<Consumer, Error>;
+}
+
+// acknowledges a single delivery, only ever called once per delivery
+#[async_trait]
+pub trait Acker: Send + Sync {
+    async fn ack(&self) -> Result<(), Error>;
+    async fn nack(&self, requeue: bool) -> Result<(), Error>;
+}
+
+pub struct Delivery {
+    routing_key: String,
+    redelivered: bool,
+    headers: Headers,
+    data: Vec<u8>,
+    acker: Box<dyn Acker>,
+}
+impl Delivery {
+    pub fn new(
+        routing_key: String,
+        redelivered: bool,
+        headers: Headers,
+        data: Vec<u8>,
+        acker: Box<dyn Acker>,
+    ) -> Self {
+        Self {
+            routing_key,
+            redelivered,
+            headers,
+            data,
+            acker,
+        }
+    }
+
+    pub fn routing_key(&self) -> &str {
+        &self.routing_key
+    }
+
+    pub fn redelivered(&self) -> bool {
+        self.redelivered
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub async fn ack(self) -> Result<(), Error> {
+        self.acker.ack().await
+    }
+
+    pub async fn nack(self, requeue: bool) -> Result<(), Error> {
+        self.acker.nack(requeue).await
+    }
+}
+
+pub struct Consumer {
+    queue: mpsc::Receiver<Delivery>,
+}
+impl Consumer {
+    // a prefetch of 0 means unlimited, but the channel needs a capacity
+    pub fn channel(prefetch: u16) -> (mpsc::Sender<Delivery>, Self) {
+        let (queue_send, queue_recv) = mpsc::channel(usize::from(prefetch.max(1)));
+
+        (queue_send, Self { queue: queue_recv })
+    }
+
+    pub async fn recv(&mut self) -> Option<Delivery> {
+        self.queue.recv().await
+    }
+}
+
+// exchanges, queues and bindings that get (re)declared on every connect
+#[derive(Clone, Debug, Default)]
+pub struct Topology {
+    exchanges: Vec<(String, ExchangeKind)>,
+    queues: Vec<String>,
+    bindings: Vec<(String, String, String)>,
+}
+impl Topology {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn exchange(mut self, name: &str, kind: ExchangeKind) -> Self {
+        self.exchanges.push((name.to_string(), kind));
+        self
+    }
+
+    #[must_use]
+    pub fn queue(mut self, name: &str) -> Self {
+        self.queues.push(name.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn bind(mut self, queue: &str, exchange: &str, binding_key: &str) -> Self {
+        self.bindings.push((
+            queue.to_string(),
+            exchange.to_string(),
+            binding_key.to_string(),
+        ));
+        self
+    }
+
+    pub async fn declare<C: Connection>(&self, conn: &C) -> Result<(), Error> {
+        for (name, kind) in &self.exchanges {
+            conn.declare_exchange(name, *kind).await?;
+        }
+        for name in &self.queues {
+            conn.declare_queue(name).await?;
+        }
+        for (queue, exchange, binding_key) in &self.bindings {
+            conn.bind_queue(queue, exchange, binding_key).await?;
+        }
+
+        Ok(())
+    }
+}
+
+// a connection that gets re-established, and the topology redeclared, whenever
+// publishing fails or a consumer ends
+pub struct Amqp<C: Connection> {
+    addr: String,
+    topology: Topology,
+    conn: RwLock<Arc<C>>,
+}
+impl<C: Connection> Amqp<C> {
+    // retries until it's connected
+    pub async fn connect(addr: &str, topology: Topology) -> Arc<Self> {
+        let conn = connect_with_backoff(addr, &topology).await;
+
+        Arc::new(Self {
+            addr: addr.to_string(),
+            topology,
+            conn: RwLock::new(Arc::new(conn)),
+        })
+    }
+
+    // publishes a message and waits for it to be confirmed, the connection is
+    // re-established before returning an error
+    pub async fn publish(
+        &self,
+        exchange: &str,
+        routing_key: &str,
+        headers: &Headers,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let conn = self.current().await;
+        let result = tokio::time::timeout(
+            CONFIRM_TIMEOUT,
+            conn.publish(exchange, routing_key, headers, data),
+        )
+        .await
+        .unwrap_or_else(|_| Err("timed out waiting for confirmation".into()));
+
+        self.reconnect_on_error(&conn, result).await
+    }
+
+    // publishes messages and waits for all of them to be confirmed, the
+    // connection is re-established before returning an error
+    pub async fn publish_batch(&self, exchange: &str, messages: &[Message]) -> Result<(), Error> {
+        let conn = self.current().await;
+        let result = tokio::time::timeout(CONFIRM_TIMEOUT, conn.publish_batch(exchange, messages))
+            .await
+            .unwrap_or_else(|_| Err("timed out waiting for confirmations".into()));
+
+        self.reconnect_on_error(&conn, result).await
+    }
+
+    async fn reconnect_on_error(
+        &self,
+        conn: &Arc<C>,
+        result: Result<(), Error>,
+    ) -> Result<(), Error> {
+        if let Err(err) = &result {
+            tracing::error!("error publishing to amqp, reconnecting: {}", err);
+            self.reconnect(conn).await;
+        }
+
+        result
+    }
+
+    // consumes a queue across reconnects, deliveries received before a
+    // reconnect can't be acked anymore and get redelivered by the broker
+    pub fn consume(self: &Arc<Self>, queue: &str, prefetch: u16) -> Consumer {
+        let (queue_send, consumer) = Consumer::channel(prefetch);
+
+        let amqp = Arc::clone(self);
+        let queue = queue.to_string();
+        tokio::spawn(async move {
+            loop {
+                let conn = amqp.current().await;
+                match conn.consume(&queue, prefetch).await {
+                    Ok(mut inner) => {
+                        while let Some(delivery) = inner.recv().await {
+                            // waits while the queue is full, unacked messages
+                            // stay with the broker
+                            if queue_send.send(delivery).await.is_err() {
+                                return;
+                            }
+                        }
+                        tracing::error!("amqp consumer ended, reconnecting");
+                    }
+                    Err(err) => tracing::error!("error consuming amqp queue: {}", err),
+                }
+
+                amqp.reconnect(&conn).await;
+            }
+        });
+
+        consumer
+    }
+
+    async fn current(&self) -> Arc<C> {
+        Arc::clone(&*self.conn.read().await)
+    }
+
+    // only reconnects if nobody else did since `failed` was handed out
+    async fn reconnect(&self, failed: &Arc<C>) {
+        let mut conn = self.conn.write().await;
+        if Arc::ptr_eq(&conn, failed) {
+            *conn = Arc::new(connect_with_backoff(&self.addr, &self.topology).await);
+        }
+    }
+}
+
+async fn connect_with_backoff<C: Connection>(addr: &str, topology: &Topology) -> C {
+    let mut backoff = MIN_BACKOFF;
+
+    loop {
+        let result = match C::connect(addr).await {
+            Ok(conn) => topology.declare(&conn).await.map(|()| conn),
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Ok(conn) => {
+                tracing::info!("connected to amqp");
+                return conn;
+            }
+            Err(err) => {
+                tracing::error!(
+                    "error connecting to amqp, retrying in {}s: {}",
+                    backoff.as_secs(),
+                    err
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+// publishes to a single exchange in the background, so callers don't have to
+// wait while the broker is unreachable
+pub struct BufferedPublisher {
+    queue: mpsc::Sender<Message>,
+    runner: JoinHandle<()>,
+}
+impl BufferedPublisher {
+    // connects in the background, messages sent while the buffer is full get
+    // dropped
+    pub fn new<C: Connection>(
+        addr: &str,
+        topology: Topology,
+        exchange: &str,
+        buffer_size: usize,
+    ) -> Self {
+        let (queue_send, queue_recv) = mpsc::channel(buffer_size);
+        let runner = tokio::spawn(Self::run::<C>(
+            addr.to_string(),
+            topology,
+            exchange.to_string(),
+            queue_recv,
+        ));
+
+        Self {
+            queue: queue_send,
+            runner,
+        }
+    }
+
+    pub fn send(&self, routing_key: String, headers: Headers, data: Vec<u8>) -> Result<(), Error> {
+        self.queue
+            .try_send((routing_key, headers, data))
+            .map_err(|err| match err {
+                TrySendError::Full(_) => "amqp buffer is full, dropping message".into(),
+                TrySendError::Closed(_) => "amqp publisher stopped".into(),
+            })
+    }
+
+    // stops accepting messages and waits for the buffered ones to be published,
+    // whatever is left after the timeout is dropped
+    pub async fn close(self, timeout: Duration) -> Result<(), Error> {
+        drop(self.queue);
+
+        let mut runner = self.runner;
+        match tokio::time::timeout(timeout, &mut runner).await {
+            Ok(result) => Ok(result?),
+            Err(_) => {
+                runner.abort();
+                Err(format!(
+                    "timed out publishing buffered messages after {}s",
+                    timeout.as_secs()
+                )
+                .into())
+            }
+        }
+    }
+
+    async fn run<C: Connection>(
+        addr: String,
+        topology: Topology,
+        exchange: String,
+        mut queue: mpsc::Receiver<Message>,
+    ) {
+        let amqp = Amqp::<C>::connect(&addr, topology).await;
+        // messages that failed to publish are kept and retried after
+        // reconnecting
+        let mut batch: Vec<Message> = Vec::with_capacity(MAX_BATCH);
+
+        loop {
+            // only returns 0 once the queue is closed and empty
+            if batch.is_empty() && queue.recv_many(&mut batch, MAX_BATCH).await == 0 {
+                return;
+            }
+
+            if amqp.publish_batch(&exchange, &batch).await.is_ok() {
+                batch.clear();
+            } else {
+                // the broker might keep rejecting them even after reconnecting
+                tokio::time::sleep(MIN_BACKOFF).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use amqprs::{
+    callbacks::{ChannelCallback, DefaultConnectionCallback},
+    channel::{
+        BasicAckArguments, BasicConsumeArguments, BasicNackArguments, BasicPublishArguments,
+        BasicQosArguments, Channel, ConfirmSelectArguments, ExchangeDeclareArguments,
+        QueueBindArguments, QueueDeclareArguments,
+    },
+    connection::OpenConnectionArguments,
+    consumer::AsyncConsumer,
+    Ack, BasicProperties, Cancel, CloseChannel, Deliver, FieldName, FieldTable, FieldValue, Nack,
+    Return,
+};
+use async_trait::async_trait;
+use tokio::sync::{mpsc, Mutex};
+
+use super::{Acker, Connection, Consumer, Delivery, Error, ExchangeKind, Headers, Message};
+
+pub struct AmqprsConnection {
+    #[expect(
+        dead_code,
+        reason = "we just don't want this to go out of scope, hence they're here"
+    )]
+    conn: amqprs::connection::Connection,
+    chan: Channel,
+    confirms: Mutex<Confirms>,
+}
+
+// publishing holds the lock until its messages are confirmed, so the
+// confirmations received are always for the messages being published
+struct Confirms {
+    recv: mpsc::UnboundedReceiver<(u64, bool)>,
+    // delivery tag of the last message published, rabbitmq numbers them from 1
+    // once confirms are enabled
+    last_tag: u64,
+}
+
+#[async_trait]
+impl Connection for AmqprsConnection {
+    async fn connect(addr: &str) -> Result<Self, Error> {
+        let amqp_addr: OpenConnectionArguments = addr.try_into()?;
+
+        let amqp_conn = amqprs::connection::Connection::open(&amqp_addr).await?;
+        amqp_conn
+            .register_callback(DefaultConnectionCallback)
+            .await?;
+
+        let (confirms_send, confirms_recv) = mpsc::unbounded_channel();
+        let amqp_chan = amqp_conn.open_channel(None).await?;
+        amqp_chan
+            .register_callback(ConfirmCallback {
+                confirms: confirms_send,
+            })
+            .await?;
+        // have rabbitmq confirm every message we publish
+        amqp_chan
+            .confirm_select(ConfirmSelectArguments::default())
+            .await?;
+
+        Ok(Self {
+            conn: amqp_conn,
+            chan: amqp_chan,
+            confirms: Mutex::new(Confirms {
+                recv: confirms_recv,
+                last_tag: 0,
+            }),
+        })
+    }
+
+    async fn declare_exchange(&self, name: &str, kind: ExchangeKind) -> Result<(), Error> {
+        Ok(self
+            .chan
+            .exchange_declare(
+                ExchangeDeclareArguments::new(name, kind.as_str())
+                    .durable(true)
+                    .finish(),
+            )
+            .await?)
+    }
+
+    async fn declare_queue(&self, name: &str) -> Result<(), Error> {
+        self.chan
+            .queue_declare(QueueDeclareArguments::new(name).durable(true).finish())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn bind_queue(
+        &self,
+        queue: &str,
+        exchange: &str,
+        binding_key: &str,
+    ) -> Result<(), Error> {
+        Ok(self
+            .chan
+            .queue_bind(QueueBindArguments::new(queue, exchange, binding_key))
+            .await?)
+    }
+
+    async fn publish(
+        &self,
+        exchange: &str,
+        routing_key: &str,
+        headers: &Headers,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.publish_batch(
+            exchange,
+            &[(routing_key.to_string(), headers.clone(), data.to_vec())],
+        )
+        .await
+    }
+
+    // every message is published before waiting on the first confirmation
+    async fn publish_batch(&self, exchange: &str, messages: &[Message]) -> Result<(), Error> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let mut confirms = self.confirms.lock().await;
+        let first_tag = confirms.last_tag + 1;
+
+        for (routing_key, headers, data) in messages {
+            let mut field_table = FieldTable::new();
+            for (key, value) in headers {
+                field_table.insert(
+                    key.as_str().try_into()?,
+                    FieldValue::S(value.as_str().try_into()?),
+                );
+            }
+
+            self.chan
+                .basic_publish(
+                    BasicProperties::default()
+                        .with_headers(field_table)
+                        .finish(),
+                    data.clone(),
+                    BasicPublishArguments::new(exchange, routing_key),
+                )
+                .await?;
+            confirms.last_tag += 1;
+        }
+
+        // rabbitmq confirms messages in the order they were published, so a
+        // confirmation covers the messages before it too
+        while let Some((tag, acked)) = confirms.recv.recv().await {
+            if !acked && tag >= first_tag {
+                return Err("rabbitmq didn't acknowledge a message".into());
+            }
+            if tag >= confirms.last_tag {
+                return Ok(());
+            }
+        }
+
+        Err("amqp channel closed".into())
+    }
+
+    async fn consume(&self, queue: &str, prefetch: u16) -> Result<Consumer, Error> {
+        // limit the amount of unacknowledged messages we get sent
+        self.chan
+            .basic_qos(BasicQosArguments::new(0, prefetch, false))
+            .await?;
+
+        let (queue_send, consumer) = Consumer::channel(prefetch);
+        self.chan
+            .basic_consume(
+                AmqpConsumer { queue: queue_send },
+                BasicConsumeArguments::new(queue, "")
+                    .manual_ack(true)
+                    .finish(),
+            )
+            .await?;
+
+        Ok(consumer)
+    }
+}
+
+// only keeps string headers
+fn headers(field_table: Option<&FieldTable>) -> Headers {
+    field_table
+        .map(|field_table| {
+            let fields: &HashMap<FieldName, FieldValue> = field_table.as_ref();
+
+            fields
+                .iter()
+                .filter_map(|(key, value)| match value {
+                    FieldValue::S(value) => Some((key.to_string(), value.to_string())),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+struct AmqpConsumer {
+    queue: mpsc::Sender<Delivery>,
+}
+
+#[async_trait]
+impl AsyncConsumer for AmqpConsumer {
+    async fn consume(
+        &mut self,
+        channel: &Channel,
+        deliver: Deliver,
+        basic_properties: BasicProperties,
+        content: Vec<u8>,
+    ) -> () {
+        tracing::info!(
+            "consume delivery {} on channel {}, content size: {}",
+            deliver,
+            channel,
+            content.len()
+        );
+
+        let delivery = Delivery::new(
+            deliver.routing_key().to_string(),
+            deliver.redelivered(),
+            headers(basic_properties.headers()),
+            content,
+            Box::new(AmqprsAcker {
+                chan: channel.clone(),
+                delivery_tag: deliver.delivery_tag(),
+            }),
+        );
+
+        // waits while the queue is full, unacked messages stay with rabbitmq
+        if let Err(err) = self.queue.send(delivery).await {
+            tracing::error!("error putting message on queue: {}", err);
+        }
+    }
+}
+
+struct AmqprsAcker {
+    chan: Channel,
+    delivery_tag: u64,
+}
+
+#[async_trait]
+impl Acker for AmqprsAcker {
+    async fn ack(&self) -> Result<(), Error> {
+        Ok(self
+            .chan
+            .basic_ack(BasicAckArguments::new(self.delivery_tag, false))
+            .await?)
+    }
+
+    async fn nack(&self, requeue: bool) -> Result<(), Error> {
+        Ok(self
+            .chan
+            .basic_nack(BasicNackArguments::new(self.delivery_tag, false, requeue))
+            .await?)
+    }
+}
+
+// forwards publisher confirms, otherwise the same as `DefaultChannelCallback`
+// sends the delivery tag of every confirmation, and whether it's an ack
+struct ConfirmCallback {
+    confirms: mpsc::UnboundedSender<(u64, bool)>,
+}
+
+#[async_trait]
+impl ChannelCallback for ConfirmCallback {
+    async fn close(
+        &mut self,
+        channel: &Channel,
+        close: CloseChannel,
+    ) -> Result<(), amqprs::error::Error> {
+        tracing::error!(
+            "handle close request for channel {}, cause: {}",
+            channel,
+            close
+        );
+        Ok(())
+    }
+
+    async fn cancel(
+        &mut self,
+        channel: &Channel,
+        cancel: Cancel,
+    ) -> Result<(), amqprs::error::Error> {
+        tracing::warn!(
+            "handle cancel request for consumer {} on channel {}",
+            cancel.consumer_tag(),
+            channel
+        );
+        Ok(())
+    }
+
+    async fn flow(
+        &mut self,
+        channel: &Channel,
+        active: bool,
+    ) -> Result<bool, amqprs::error::Error> {
+        tracing::info!(
+            "handle flow request active={} for channel {}",
+            active,
+            channel
+        );
+        Ok(true)
+    }
+
+    // sending only fails once the connection is gone, and then nobody's waiting
+    async fn publish_ack(&mut self, _channel: &Channel, ack: Ack) {
+        let _ = self.confirms.send((ack.delivery_tag(), true));
+    }
+
+    async fn publish_nack(&mut self, _channel: &Channel, nack: Nack) {
+        let _ = self.confirms.send((nack.delivery_tag(), false));
+    }
+
+    async fn publish_return(
+        &mut self,
+        channel: &Channel,
+        ret: Return,
+        _basic_properties: BasicProperties,
+        content: Vec<u8>,
+    ) {
+        tracing::warn!(
+            "handle publish return {} on channel {}, content size: {}",
+            ret,
+            channel,
+            content.len()
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use async_trait::async_trait;
+use futures_util::StreamExt as _;
+use lapin::{
+    acker,
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, BasicPublishOptions,
+        BasicQosOptions, ConfirmSelectOptions, ExchangeDeclareOptions, QueueBindOptions,
+        QueueDeclareOptions,
+    },
+    types::{AMQPValue, FieldTable},
+    BasicProperties, Channel, ConnectionProperties,
+};
+
+use super::{Acker, Connection, Consumer, Delivery, Error, ExchangeKind, Headers, Message};
+
+pub struct LapinConnection {
+    #[expect(
+        dead_code,
+        reason = "we just don't want this to go out of scope, hence they're here"
+    )]
+    conn: lapin::Connection,
+    chan: Channel,
+}
+
+#[async_trait]
+impl Connection for LapinConnection {
+    async fn connect(addr: &str) -> Result<Self, Error> {
+        let options = ConnectionProperties::default()
+            .with_executor(tokio_executor_trait::Tokio::current())
+            .with_reactor(tokio_reactor_trait::Tokio);
+        let conn = lapin::Connection::connect(addr, options).await?;
+        let chan = conn.create_channel().await?;
+        // have rabbitmq confirm every message we publish
+        chan.confirm_select(ConfirmSelectOptions::default()).await?;
+
+        Ok(Self { conn, chan })
+    }
+
+    async fn declare_exchange(&self, name: &str, kind: ExchangeKind) -> Result<(), Error> {
+        let kind = match kind {
+            ExchangeKind::Direct => lapin::ExchangeKind::Direct,
+            ExchangeKind::Fanout => lapin::ExchangeKind::Fanout,
+            ExchangeKind::Topic => lapin::ExchangeKind::Topic,
+        };
+
+        Ok(self
+            .chan
+            .exchange_declare(
+                name,
+                kind,
+                ExchangeDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await?)
+    }
+
+    async fn declare_queue(&self, name: &str) -> Result<(), Error> {
+        self.chan
+            .queue_declare(
+                name,
+                QueueDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn bind_queue(
+        &self,
+        queue: &str,
+        exchange: &str,
+        binding_key: &str,
+    ) -> Result<(), Error> {
+        Ok(self
+            .chan
+            .queue_bind(
+                queue,
+                exchange,
+                binding_key,
+                QueueBindOptions::default(),
+                FieldTable::default(),
+            )
+            .await?)
+    }
+
+    async fn publish(
+        &self,
+        exchange: &str,
+        routing_key: &str,
+        headers: &Headers,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let confirmation = self
+            .chan
+            .basic_publish(
+                exchange,
+                routing_key,
+                BasicPublishOptions::default(),
+                data,
+                BasicProperties::default().with_headers(field_table(headers)),
+            )
+            .await?
+            .await?;
+
+        if !confirmation.is_ack() {
+            return Err("rabbitmq didn't acknowledge the message".into());
+        }
+
+        Ok(())
+    }
+
+    // every message is published before waiting on the first confirmation
+    async fn publish_batch(&self, exchange: &str, messages: &[Message]) -> Result<(), Error> {
+        let mut confirms = Vec::with_capacity(messages.len());
+        for (routing_key, headers, data) in messages {
+            confirms.push(
+                self.chan
+                    .basic_publish(
+                        exchange,
+                        routing_key,
+                        BasicPublishOptions::default(),
+                        data,
+                        BasicProperties::default().with_headers(field_table(headers)),
+                    )
+                    .await?,
+            );
+        }
+
+        for confirm in confirms {
+            if !confirm.await?.is_ack() {
+                return Err("rabbitmq didn't acknowledge a message".into());
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn consume(&self, queue: &str, prefetch: u16) -> Result<Consumer, Error> {
+        // limit the amount of unacknowledged messages we get sent
+        self.chan
+            .basic_qos(prefetch, BasicQosOptions::default())
+            .await?;
+        let mut lapin_consumer = self
+            .chan
+            .basic_consume(
+                queue,
+                "tulpje",
+                BasicConsumeOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+
+        let (queue_send, consumer) = Consumer::channel(prefetch);
+        tokio::spawn(async move {
+            loop {
+                let delivery = match lapin_consumer.next().await {
+                    Some(Ok(delivery)) => Delivery::new(
+                        delivery.routing_key.as_str().to_owned(),
+                        delivery.redelivered,
+                        headers(delivery.properties.headers().as_ref()),
+                        delivery.data,
+                        Box::new(LapinAcker(delivery.acker)),
+                    ),
+                    Some(Err(err)) => {
+                        tracing::error!("error receiving message: {}", err);
+                        continue;
+                    }
+                    None => break,
+                };
+
+                // waits while the queue is full, unacked messages stay with rabbitmq
+                if queue_send.send(delivery).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(consumer)
+    }
+}
+
+fn field_table(headers: &Headers) -> FieldTable {
+    let mut field_table = FieldTable::default();
+    for (key, value) in headers {
+        field_table.insert(
+            key.as_str().into(),
+            AMQPValue::LongString(value.clone().into()),
+        );
+    }
+
+    field_table
+}
+
+// only keeps string headers
+fn headers(field_table: Option<&FieldTable>) -> Headers {
+    field_table
+        .map(|field_table| {
+            field_table
+                .inner()
+                .iter()
+                .filter_map(|(key, value)| match value {
+                    AMQPValue::LongString(value) => Some((
+                        key.as_str().to_owned(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+struct LapinAcker(acker::Acker);
+
+#[async_trait]
+impl Acker for LapinAcker {
+    async fn ack(&self) -> Result<(), Error> {
+        Ok(self.0.ack(BasicAckOptions::default()).await?)
+    }
+
+    async fn nack(&self, requeue: bool) -> Result<(), Error> {
+        Ok(self
+            .0
+            .nack(BasicNackOptions {
+                requeue,
+                ..Default::default()
+            })
+            .await?)
+    }
+}
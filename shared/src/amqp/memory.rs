@@ -0,0 +1,388 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError},
+};
+
+use async_trait::async_trait;
+use tokio::sync::Notify;
+
+use super::{Acker, Connection, Consumer, Delivery, Error, ExchangeKind, Headers};
+
+// in-memory broker for tests, connections to the same address share a broker,
+// nothing is persisted and connections are never lost
+pub struct MemoryConnection {
+    broker: Arc<Mutex<Broker>>,
+}
+
+#[derive(Default)]
+struct Broker {
+    exchanges: HashMap<String, ExchangeKind>,
+    queues: HashMap<String, Queue>,
+    // queue, exchange, binding key
+    bindings: Vec<(String, String, String)>,
+}
+impl Broker {
+    fn route(&self, exchange: &str, routing_key: &str) -> Result<Vec<String>, Error> {
+        // the default exchange routes straight to the queue with that name
+        if exchange.is_empty() {
+            return Ok(vec![routing_key.to_string()]);
+        }
+
+        let Some(kind) = self.exchanges.get(exchange) else {
+            return Err(format!("exchange {} not declared", exchange).into());
+        };
+
+        Ok(self
+            .bindings
+            .iter()
+            .filter(|(_, bound_exchange, binding_key)| {
+                bound_exchange == exchange
+                    && match kind {
+                        ExchangeKind::Direct => binding_key == routing_key,
+                        ExchangeKind::Fanout => true,
+                        ExchangeKind::Topic => topic_matches(binding_key, routing_key),
+                    }
+            })
+            .map(|(queue, _, _)| queue.clone())
+            .collect())
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    messages: VecDeque<Message>,
+    notify: Arc<Notify>,
+}
+
+#[derive(Clone)]
+struct Message {
+    routing_key: String,
+    redelivered: bool,
+    headers: Headers,
+    data: Vec<u8>,
+}
+
+fn brokers() -> MutexGuard<'static, HashMap<String, Arc<Mutex<Broker>>>> {
+    static BROKERS: OnceLock<Mutex<HashMap<String, Arc<Mutex<Broker>>>>> = OnceLock::new();
+
+    BROKERS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+fn lock(broker: &Mutex<Broker>) -> MutexGuard<'_, Broker> {
+    broker.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// puts a message at the back, or the front when it's being requeued
+fn enqueue(broker: &Mutex<Broker>, queue: &str, message: Message, front: bool) {
+    let mut broker = lock(broker);
+    let Some(queue) = broker.queues.get_mut(queue) else {
+        // like rabbitmq, messages for queues that don't exist get dropped
+        return;
+    };
+
+    if front {
+        queue.messages.push_front(message);
+    } else {
+        queue.messages.push_back(message);
+    }
+    queue.notify.notify_one();
+}
+
+#[async_trait]
+impl Connection for MemoryConnection {
+    async fn connect(addr: &str) -> Result<Self, Error> {
+        Ok(Self {
+            broker: Arc::clone(brokers().entry(addr.to_string()).or_default()),
+        })
+    }
+
+    async fn declare_exchange(&self, name: &str, kind: ExchangeKind) -> Result<(), Error> {
+        let mut broker = lock(&self.broker);
+        match broker.exchanges.get(name) {
+            Some(existing) if *existing != kind => Err(format!(
+                "exchange {} already declared as {}",
+                name,
+                existing.as_str()
+            )
+            .into()),
+            Some(_) => Ok(()),
+            None => {
+                broker.exchanges.insert(name.to_string(), kind);
+                Ok(())
+            }
+        }
+    }
+
+    async fn declare_queue(&self, name: &str) -> Result<(), Error> {
+        lock(&self.broker)
+            .queues
+            .entry(name.to_string())
+            .or_default();
+
+        Ok(())
+    }
+
+    async fn bind_queue(
+        &self,
+        queue: &str,
+        exchange: &str,
+        binding_key: &str,
+    ) -> Result<(), Error> {
+        let mut broker = lock(&self.broker);
+        if !broker.queues.contains_key(queue) {
+            return Err(format!("queue {} not declared", queue).into());
+        }
+        if !broker.exchanges.contains_key(exchange) {
+            return Err(format!("exchange {} not declared", exchange).into());
+        }
+
+        let binding = (
+            queue.to_string(),
+            exchange.to_string(),
+            binding_key.to_string(),
+        );
+        if !broker.bindings.contains(&binding) {
+            broker.bindings.push(binding);
+        }
+
+        Ok(())
+    }
+
+    async fn publish(
+        &self,
+        exchange: &str,
+        routing_key: &str,
+        headers: &Headers,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let queues = lock(&self.broker).route(exchange, routing_key)?;
+
+        let message = Message {
+            routing_key: routing_key.to_string(),
+            redelivered: false,
+            headers: headers.clone(),
+            data: data.to_vec(),
+        };
+        for queue in queues {
+            enqueue(&self.broker, &queue, message.clone(), false);
+        }
+
+        Ok(())
+    }
+
+    // prefetch only limits how many deliveries are buffered in the consumer,
+    // not how many are unacked
+    async fn consume(&self, queue: &str, prefetch: u16) -> Result<Consumer, Error> {
+        let notify = lock(&self.broker)
+            .queues
+            .get(queue)
+            .map(|declared| Arc::clone(&declared.notify))
+            .ok_or_else(|| format!("queue {} not declared", queue))?;
+
+        let (queue_send, consumer) = Consumer::channel(prefetch);
+        let broker = Arc::clone(&self.broker);
+        let queue = queue.to_string();
+        tokio::spawn(async move {
+            loop {
+                let message = lock(&broker)
+                    .queues
+                    .get_mut(&queue)
+                    .and_then(|queue| queue.messages.pop_front());
+                let Some(message) = message else {
+                    notify.notified().await;
+                    continue;
+                };
+
+                let delivery = Delivery::new(
+                    message.routing_key.clone(),
+                    message.redelivered,
+                    message.headers.clone(),
+                    message.data.clone(),
+                    Box::new(MemoryAcker {
+                        broker: Arc::clone(&broker),
+                        queue: queue.clone(),
+                        message: message.clone(),
+                    }),
+                );
+
+                // consumer was dropped, put the message back for the next one
+                if queue_send.send(delivery).await.is_err() {
+                    enqueue(&broker, &queue, message, true);
+                    return;
+                }
+            }
+        });
+
+        Ok(consumer)
+    }
+}
+
+struct MemoryAcker {
+    broker: Arc<Mutex<Broker>>,
+    queue: String,
+    message: Message,
+}
+
+#[async_trait]
+impl Acker for MemoryAcker {
+    async fn ack(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn nack(&self, requeue: bool) -> Result<(), Error> {
+        if requeue {
+            let message = Message {
+                redelivered: true,
+                ..self.message.clone()
+            };
+            enqueue(&self.broker, &self.queue, message, true);
+        }
+
+        Ok(())
+    }
+}
+
+// whether a routing key matches a topic binding key, `*` matches exactly one
+// word and `#` matches zero or more words
+fn topic_matches(binding_key: &str, routing_key: &str) -> bool {
+    fn matches(binding: &[&str], key: &[&str]) -> bool {
+        match (binding.split_first(), key.split_first()) {
+            (None, None) => true,
+            (Some((&"#", binding_rest)), _) => {
+                matches(binding_rest, key)
+                    || key
+                        .split_first()
+                        .is_some_and(|(_, key_rest)| matches(binding, key_rest))
+            }
+            (Some((&"*", binding_rest)), Some((_, key_rest))) => matches(binding_rest, key_rest),
+            (Some((word, binding_rest)), Some((key_word, key_rest))) => {
+                word == key_word && matches(binding_rest, key_rest)
+            }
+            _ => false,
+        }
+    }
+
+    matches(
+        &binding_key.split('.').collect::<Vec<_>>(),
+        &routing_key.split('.').collect::<Vec<_>>(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amqp::{BufferedPublisher, Topology};
+
+    #[test]
+    fn topic_matches_test() {
+        assert!(topic_matches(
+            "event.MESSAGE_CREATE.shard.#",
+            "event.MESSAGE_CREATE.shard.3"
+        ));
+        assert!(topic_matches(
+            "event.MESSAGE_CREATE.shard.#",
+            "event.MESSAGE_CREATE.shard.3.partition.1"
+        ));
+        assert!(topic_matches(
+            "event.MESSAGE_CREATE.shard.*.partition.1",
+            "event.MESSAGE_CREATE.shard.3.partition.1"
+        ));
+        assert!(topic_matches("#", "event.READY.shard.0"));
+
+        assert!(!topic_matches(
+            "event.MESSAGE_CREATE.shard.*.partition.1",
+            "event.MESSAGE_CREATE.shard.3.partition.2"
+        ));
+        assert!(!topic_matches(
+            "event.MESSAGE_CREATE.shard.*.partition.1",
+            "event.MESSAGE_CREATE.shard.3"
+        ));
+        assert!(!topic_matches(
+            "event.MESSAGE_CREATE.shard.#",
+            "event.MESSAGE_DELETE.shard.3"
+        ));
+    }
+
+    #[tokio::test]
+    async fn consume_test() {
+        let conn = MemoryConnection::connect("memory://consume_test")
+            .await
+            .expect("couldn't connect");
+        Topology::new()
+            .exchange("discord", ExchangeKind::Topic)
+            .queue("discord")
+            .bind("discord", "discord", "event.MESSAGE_CREATE.shard.#")
+            .declare(&conn)
+            .await
+            .expect("couldn't declare topology");
+        let mut consumer = conn.consume("discord", 10).await.expect("couldn't consume");
+
+        let headers = Headers::from([("x-test".to_string(), "yes".to_string())]);
+        for routing_key in [
+            "event.MESSAGE_DELETE.shard.0",
+            "event.MESSAGE_CREATE.shard.0",
+        ] {
+            conn.publish("discord", routing_key, &headers, b"event")
+                .await
+                .expect("couldn't publish");
+        }
+
+        // unbound events don't get routed, requeued ones get redelivered
+        let delivery = consumer.recv().await.expect("consumer ended");
+        assert_eq!(delivery.routing_key(), "event.MESSAGE_CREATE.shard.0");
+        assert_eq!(delivery.headers(), &headers);
+        assert!(!delivery.redelivered());
+        delivery.nack(true).await.expect("couldn't nack");
+
+        let delivery = consumer.recv().await.expect("consumer ended");
+        assert_eq!(delivery.data(), b"event");
+        assert!(delivery.redelivered());
+        delivery.ack().await.expect("couldn't ack");
+    }
+
+    #[tokio::test]
+    async fn buffered_publisher_close_test() {
+        let topology = Topology::new()
+            .exchange("discord", ExchangeKind::Topic)
+            .queue("discord")
+            .bind("discord", "discord", "#");
+        let publisher = BufferedPublisher::new::<MemoryConnection>(
+            "memory://buffered_publisher_close_test",
+            topology,
+            "discord",
+            10,
+        );
+
+        for shard in 0..3 {
+            publisher
+                .send(
+                    format!("event.READY.shard.{}", shard),
+                    Headers::new(),
+                    b"event".to_vec(),
+                )
+                .expect("couldn't send");
+        }
+
+        // everything buffered is published before close returns
+        publisher
+            .close(std::time::Duration::from_secs(5))
+            .await
+            .expect("couldn't close");
+
+        let conn = MemoryConnection::connect("memory://buffered_publisher_close_test")
+            .await
+            .expect("couldn't connect");
+        let mut consumer = conn.consume("discord", 10).await.expect("couldn't consume");
+        for shard in 0..3 {
+            let delivery = consumer.recv().await.expect("consumer ended");
+            assert_eq!(
+                delivery.routing_key(),
+                format!("event.READY.shard.{}", shard)
+            );
+            delivery.ack().await.expect("couldn't ack");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use twilight_model::id::{
+    marker::{GuildMarker, MessageMarker},
+    Id,
+};
+
+// discord entities the gateway keeps in redis as json, only guilds the gateway
+// received a GUILD_CREATE for are cached, the channel, role and emoji hashes of
+// those are complete, members only contain the ones we've seen
+//
+// tulpje:cache:guild:{guild_id}           guild without channels, roles, etc.
+// tulpje:cache:guild:{guild_id}:channels  hash of channel id to channel
+// tulpje:cache:guild:{guild_id}:roles     hash of role id to role
+// tulpje:cache:guild:{guild_id}:emojis    hash of emoji id to emoji, only kept
+//                                         when the gateway receives emoji
+//                                         updates
+// tulpje:cache:guild:{guild_id}:emojis:cached
+//                                         exists when the emoji hash is kept,
+//                                         which doesn't exist without emojis
+// tulpje:cache:guild:{guild_id}:members   hash of user id to member, only kept
+//                                         when the gateway receives member
+//                                         updates
+// tulpje:cache:message:{message_id}       message, expires after MESSAGE_TTL
+// tulpje:cache:heartbeat                  exists while a gateway keeps the cache
+//                                         up-to-date, expires after HEARTBEAT_TTL
+//
+// nothing is read from the cache without a heartbeat, so it's not used when the
+// gateways stopped caching or aren't running
+
+// recent messages expire after this many seconds
+pub const MESSAGE_TTL: u64 = 60 * 60;
+
+pub const HEARTBEAT_KEY: &str = "tulpje:cache:heartbeat";
+
+// gateways refresh the heartbeat every third of this many seconds
+pub const HEARTBEAT_TTL: u64 = 60;
+
+pub fn guild_key(guild_id: Id<GuildMarker>) -> String {
+    format!("tulpje:cache:guild:{}", guild_id)
+}
+
+pub fn channels_key(guild_id: Id<GuildMarker>) -> String {
+    format!("tulpje:cache:guild:{}:channels", guild_id)
+}
+
+pub fn roles_key(guild_id: Id<GuildMarker>) -> String {
+    format!("tulpje:cache:guild:{}:roles", guild_id)
+}
+
+pub fn emojis_key(guild_id: Id<GuildMarker>) -> String {
+    format!("tulpje:cache:guild:{}:emojis", guild_id)
+}
+
+pub fn emojis_cached_key(guild_id: Id<GuildMarker>) -> String {
+    format!("tulpje:cache:guild:{}:emojis:cached", guild_id)
+}
+
+pub fn members_key(guild_id: Id<GuildMarker>) -> String {
+    format!("tulpje:cache:guild:{}:members", guild_id)
+}
+
+pub fn message_key(message_id: Id<MessageMarker>) -> String {
+    format!("tulpje:cache:message:{}", message_id)
+}
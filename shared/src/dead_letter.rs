@@ -0,0 +1,13 @@
+// events that couldn't be parsed or kept failing get published to this
+// exchange by the handler, and end up in the queue of the same name
+pub const EXCHANGE: &str = "discord.dead-letter";
+pub const QUEUE: &str = "discord.dead-letter";
+
+// the error that caused the event to be dead-lettered
+pub const HEADER_ERROR: &str = "x-tulpje-error";
+// `DiscordEventMeta` serialized as json, missing if the event couldn't be parsed
+pub const HEADER_META: &str = "x-tulpje-meta";
+// routing key the event was originally published with, used when replaying
+pub const HEADER_ROUTING_KEY: &str = "x-tulpje-routing-key";
+// unix timestamp of when the event was dead-lettered
+pub const HEADER_FAILED_AT: &str = "x-tulpje-failed-at";
@@ -0,0 +1,105 @@
+use crate::{
+    amqp::{Error, Headers},
+    DiscordEvent, DiscordEventMeta,
+};
+
+// events are published as the raw gateway payload with the meta in the message
+// headers, messages without a version header are the old json `DiscordEvent`
+// format and are still accepted, so handlers should be upgraded first
+pub const VERSION: u32 = 2;
+
+pub const HEADER_VERSION: &str = "x-tulpje-version";
+pub const HEADER_ENCODING: &str = "x-tulpje-encoding";
+pub const HEADER_UUID: &str = "x-tulpje-uuid";
+pub const HEADER_SHARD: &str = "x-tulpje-shard";
+
+pub const ENCODING_IDENTITY: &str = "identity";
+pub const ENCODING_ZSTD: &str = "zstd";
+
+const ZSTD_LEVEL: i32 = 3;
+
+pub fn encode(event: &DiscordEvent, compress: bool) -> Result<(Headers, Vec<u8>), Error> {
+    let (encoding, data) = if compress {
+        (
+            ENCODING_ZSTD,
+            zstd::encode_all(event.payload.as_bytes(), ZSTD_LEVEL)?,
+        )
+    } else {
+        (ENCODING_IDENTITY, event.payload.as_bytes().to_vec())
+    };
+
+    let headers = Headers::from([
+        (HEADER_VERSION.to_string(), VERSION.to_string()),
+        (HEADER_ENCODING.to_string(), encoding.to_string()),
+        (HEADER_UUID.to_string(), event.meta.uuid.to_string()),
+        (HEADER_SHARD.to_string(), event.meta.shard.to_string()),
+    ]);
+
+    Ok((headers, data))
+}
+
+pub fn decode(headers: &Headers, data: &[u8]) -> Result<DiscordEvent, Error> {
+    let Some(version) = headers.get(HEADER_VERSION) else {
+        return Ok(serde_json::from_slice(data)?);
+    };
+    if version.parse::<u32>()? != VERSION {
+        return Err(format!("unsupported envelope version {}", version).into());
+    }
+
+    let payload = match headers.get(HEADER_ENCODING).map(String::as_str) {
+        None | Some(ENCODING_IDENTITY) => data.to_vec(),
+        Some(ENCODING_ZSTD) => zstd::decode_all(data)?,
+        Some(encoding) => return Err(format!("unsupported envelope encoding {}", encoding).into()),
+    };
+
+    Ok(DiscordEvent {
+        meta: meta(headers)?,
+        payload: String::from_utf8(payload)?,
+    })
+}
+
+// the meta of a versioned envelope, without having to decode the payload
+pub fn meta(headers: &Headers) -> Result<DiscordEventMeta, Error> {
+    let header = |key: &str| {
+        headers
+            .get(key)
+            .ok_or_else(|| format!("envelope is missing the {} header", key))
+    };
+
+    Ok(DiscordEventMeta {
+        uuid: header(HEADER_UUID)?.parse()?,
+        shard: header(HEADER_SHARD)?.parse()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_test() {
+        let event = DiscordEvent::new(3, String::from(r#"{"op":0,"t":"READY"}"#));
+
+        for compress in [false, true] {
+            let (headers, data) = encode(&event, compress).expect("couldn't encode event");
+            let decoded = decode(&headers, &data).expect("couldn't decode event");
+
+            assert_eq!(decoded.meta.uuid, event.meta.uuid);
+            assert_eq!(decoded.meta.shard, event.meta.shard);
+            assert_eq!(decoded.payload, event.payload);
+        }
+    }
+
+    #[test]
+    fn legacy_envelope_test() {
+        let event = DiscordEvent::new(3, String::from(r#"{"op":0,"t":"READY"}"#));
+        let data = serde_json::to_vec(&event).expect("couldn't serialize event");
+
+        let decoded = decode(&Headers::new(), &data).expect("couldn't decode event");
+        assert_eq!(decoded.meta.uuid, event.meta.uuid);
+        assert_eq!(decoded.payload, event.payload);
+
+        let headers = Headers::from([(HEADER_VERSION.to_string(), String::from("3"))]);
+        assert!(decode(&headers, &data).is_err());
+    }
+}
@@ -1,9 +1,16 @@
 use serde::{Deserialize, Serialize};
 use twilight_model::id::{marker::ApplicationMarker, Id};
 
+pub mod amqp;
+pub mod cache;
 pub mod color;
+pub mod dead_letter;
+pub mod envelope;
 pub mod metrics;
+pub mod routing;
+pub mod shard_plan;
 pub mod shard_state;
+pub mod shutdown;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DiscordEvent {
@@ -54,6 +61,17 @@ pub fn format_significant_duration(total_secs: u64) -> String {
     }
 }
 
+// used to tell processes apart in the stats, docker sets HOSTNAME to the
+// container id
+pub fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+        .unwrap_or_else(|| "unknown".into())
+}
+
 pub fn is_pk_proxy(application_id: &Option<Id<ApplicationMarker>>) -> bool {
     application_id.is_some_and(|id| id.get() == 466378653216014359) // PluralKit Application ID
 }
@@ -1,4 +1,7 @@
-use std::{error::Error, time::Duration};
+use std::{
+    error::Error,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use bb8_redis::{redis::AsyncCommands as _, RedisConnectionManager};
 use metrics_exporter_prometheus::PrometheusBuilder;
@@ -25,17 +28,40 @@ pub fn install(
     Ok(())
 }
 
+// hash of process name to `Metrics` json
+pub const METRICS_KEY: &str = "tulpje:metrics";
+
+// metrics are saved every 10 seconds, ones that haven't been saved in this long
+// belong to a process that's gone, in seconds
+pub const STALE_AFTER: u64 = 60;
+
 #[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
 pub struct Metrics {
     pub name: String,
+    pub host: String,
 
     pub cpu_usage: f32,
     pub memory_usage: u64,
+
+    pub updated_at: u64,
+}
+
+impl Metrics {
+    pub fn is_stale(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_secs();
+
+        now.saturating_sub(self.updated_at) > STALE_AFTER
+    }
 }
 
 #[derive(Clone)]
 struct MetricsManager {
     name: String,
+    host: String,
 
     interval_ms: u64,
     prev_cpu_ms: u64,
@@ -52,6 +78,7 @@ impl MetricsManager {
     ) -> Self {
         Self {
             name,
+            host: crate::hostname(),
 
             interval_ms: 10_000,
             prev_cpu_ms: 0,
@@ -94,16 +121,21 @@ impl MetricsManager {
 
         let json_metrics = serde_json::to_string(&Metrics {
             name: self.name.clone(),
+            host: self.host.clone(),
 
             cpu_usage,
             memory_usage,
+
+            updated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("time went backwards")
+                .as_secs(),
         })?;
 
-        // TODO: Implement IDs for the instances
         self.redis
             .get()
             .await?
-            .hset::<&str, &str, String, ()>("tulpje:metrics", &self.name, json_metrics)
+            .hset::<&str, &str, String, ()>(METRICS_KEY, &self.name, json_metrics)
             .await?;
 
         Ok(())
@@ -0,0 +1,98 @@
+// the gateway publishes events to this topic exchange, handlers bind their
+// queue to it for the events they handle
+pub const EXCHANGE: &str = "discord";
+
+// queue handlers consume from, when partitioning events every handler gets
+// its own queue
+pub fn queue(partition: Option<u32>) -> String {
+    partition.map_or_else(
+        || String::from("discord"),
+        |partition| format!("discord.partition.{}", partition),
+    )
+}
+
+// routing key for an event, e.g. `event.MESSAGE_CREATE.shard.3`, or
+// `event.MESSAGE_CREATE.shard.3.partition.1` when partitioning events
+pub fn routing_key(event: &str, shard: u32, partition: Option<u32>) -> String {
+    partition.map_or_else(
+        || format!("event.{}.shard.{}", event, shard),
+        |partition| format!("event.{}.shard.{}.partition.{}", event, shard, partition),
+    )
+}
+
+// binding key matching an event from any shard, either from any partition or
+// only from the given one
+pub fn binding_key(event: &str, partition: Option<u32>) -> String {
+    partition.map_or_else(
+        || format!("event.{}.shard.#", event),
+        |partition| format!("event.{}.shard.*.partition.{}", event, partition),
+    )
+}
+
+// the partition a guild's events go to, uses jump consistent hashing so
+// changing the amount of partitions moves as few guilds as possible
+// see https://arxiv.org/abs/1406.2294
+#[expect(
+    clippy::cast_precision_loss,
+    reason = "the algorithm works on floats, precision loss is expected"
+)]
+pub fn partition(guild_id: u64, partitions: u32) -> u32 {
+    let mut key = guild_id;
+    let mut bucket: i64 = -1;
+    let mut next: i64 = 0;
+
+    while next < i64::from(partitions.max(1)) {
+        bucket = next;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1_u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+
+    bucket as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routing_key_test() {
+        assert_eq!(
+            routing_key("MESSAGE_CREATE", 3, None),
+            "event.MESSAGE_CREATE.shard.3"
+        );
+        assert_eq!(
+            routing_key("MESSAGE_CREATE", 3, Some(1)),
+            "event.MESSAGE_CREATE.shard.3.partition.1"
+        );
+        assert_eq!(
+            binding_key("MESSAGE_CREATE", None),
+            "event.MESSAGE_CREATE.shard.#"
+        );
+        assert_eq!(
+            binding_key("MESSAGE_CREATE", Some(1)),
+            "event.MESSAGE_CREATE.shard.*.partition.1"
+        );
+    }
+
+    #[test]
+    fn partition_test() {
+        for guild_id in (0..1_000_u64).map(|n| n * 7_919 + 1_000_000) {
+            assert_eq!(
+                partition(guild_id, 1),
+                0,
+                "a single partition gets everything"
+            );
+
+            for partitions in 1..10 {
+                let current = partition(guild_id, partitions);
+                let next = partition(guild_id, partitions + 1);
+
+                assert!(current < partitions, "partition out of range");
+                assert!(
+                    next == current || next == partitions,
+                    "guilds should only move to the new partition"
+                );
+            }
+        }
+    }
+}
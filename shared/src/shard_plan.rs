@@ -0,0 +1,376 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bb8_redis::{
+    redis::{AsyncCommands as _, ExistenceCheck, Script, SetExpiry, SetOptions},
+    RedisConnectionManager,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::amqp::Error;
+
+// the shard layout gateways run, written by the manager, every reshard is a new
+// generation of shards that's brought up next to the current one:
+//
+//  stable        only the current generation runs
+//  starting      the next generation identifies, only the current one publishes
+//  cutting_over  both generations publish, shards of the next generation
+//                acknowledge they're publishing
+//
+// once every shard of the next generation acknowledged it, the next generation
+// becomes the current one and the old one shuts down, so no events are missed
+// during the handover, some might get published twice though, gateways claim a
+// shard of a generation and renew the claim while they run it
+pub const PLAN_KEY: &str = "tulpje:shard_plan";
+
+// claims expire after this many seconds unless they're renewed
+pub const CLAIM_TTL: u64 = 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Stable,
+    Starting,
+    CuttingOver,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardSet {
+    pub generation: u64,
+    pub shard_count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShardPlan {
+    // highest generation handed out, so aborted ones are never reused
+    pub generation: u64,
+    pub current: ShardSet,
+    pub next: Option<ShardSet>,
+    pub phase: Phase,
+    // unix timestamp of the last phase change
+    pub updated_at: u64,
+}
+
+impl ShardPlan {
+    pub fn new(shard_count: u32) -> Self {
+        Self {
+            generation: 1,
+            current: ShardSet {
+                generation: 1,
+                shard_count,
+            },
+            next: None,
+            phase: Phase::Stable,
+            updated_at: unix_now(),
+        }
+    }
+
+    #[must_use]
+    pub fn start_resharding(&self, shard_count: u32) -> Self {
+        let generation = self.generation + 1;
+
+        Self {
+            generation,
+            current: self.current,
+            next: Some(ShardSet {
+                generation,
+                shard_count,
+            }),
+            phase: Phase::Starting,
+            updated_at: unix_now(),
+        }
+    }
+
+    #[must_use]
+    pub fn cut_over(&self) -> Self {
+        Self {
+            phase: Phase::CuttingOver,
+            updated_at: unix_now(),
+            ..self.clone()
+        }
+    }
+
+    #[must_use]
+    pub fn finish(&self) -> Self {
+        Self {
+            generation: self.generation,
+            current: self.next.unwrap_or(self.current),
+            next: None,
+            phase: Phase::Stable,
+            updated_at: unix_now(),
+        }
+    }
+
+    #[must_use]
+    pub fn abort(&self) -> Self {
+        Self {
+            generation: self.generation,
+            current: self.current,
+            next: None,
+            phase: Phase::Stable,
+            updated_at: unix_now(),
+        }
+    }
+
+    // the generation newly started gateways should claim a shard of
+    pub fn joining(&self) -> ShardSet {
+        self.next.unwrap_or(self.current)
+    }
+
+    // the generation whose shards publish their events, or are taking over
+    // publishing them while cutting over
+    pub fn publishing(&self) -> ShardSet {
+        match (self.phase, self.next) {
+            (Phase::CuttingOver, Some(next)) => next,
+            _ => self.current,
+        }
+    }
+
+    // whether shards of a generation should publish their events, both
+    // generations do while cutting over
+    pub fn publishes(&self, generation: u64) -> bool {
+        self.current.generation == generation || self.publishing().generation == generation
+    }
+
+    // whether shards of a generation should shut down
+    pub fn retired(&self, generation: u64) -> bool {
+        let active = self.current.generation == generation
+            || self.next.is_some_and(|next| next.generation == generation);
+
+        !active
+    }
+
+    // seconds since the last phase change
+    pub fn age(&self) -> u64 {
+        unix_now().saturating_sub(self.updated_at)
+    }
+}
+
+fn claim_key(generation: u64, shard_id: u32) -> String {
+    format!("tulpje:shard_plan:{}:claim:{}", generation, shard_id)
+}
+
+fn ready_key(generation: u64) -> String {
+    format!("tulpje:shard_plan:{}:ready", generation)
+}
+
+fn publishing_key(generation: u64) -> String {
+    format!("tulpje:shard_plan:{}:publishing", generation)
+}
+
+pub async fn load(redis: &bb8::Pool<RedisConnectionManager>) -> Result<Option<ShardPlan>, Error> {
+    let json = redis
+        .get()
+        .await?
+        .get::<&str, Option<String>>(PLAN_KEY)
+        .await?;
+
+    Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+}
+
+pub async fn save(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    plan: &ShardPlan,
+) -> Result<(), Error> {
+    redis
+        .get()
+        .await?
+        .set::<&str, String, ()>(PLAN_KEY, serde_json::to_string(plan)?)
+        .await?;
+
+    Ok(())
+}
+
+// claims the first unclaimed shard of a generation for a process
+pub async fn claim(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    set: ShardSet,
+    process: &str,
+) -> Result<Option<u32>, Error> {
+    let mut conn = redis.get().await?;
+
+    for shard_id in 0..set.shard_count {
+        let claimed = conn
+            .set_options::<String, &str, Option<String>>(
+                claim_key(set.generation, shard_id),
+                process,
+                SetOptions::default()
+                    .conditional_set(ExistenceCheck::NX)
+                    .with_expiration(SetExpiry::EX(CLAIM_TTL)),
+            )
+            .await?;
+
+        if claimed.is_some() {
+            return Ok(Some(shard_id));
+        }
+    }
+
+    Ok(None)
+}
+
+// returns false if the claim expired, it might've been claimed by another
+// process since, so it's only renewed if it's still ours
+pub async fn renew_claim(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    generation: u64,
+    shard_id: u32,
+    process: &str,
+) -> Result<bool, Error> {
+    let script = Script::new(
+        r"
+        if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+            return 0
+        end
+        redis.call('EXPIRE', KEYS[1], ARGV[2])
+        return 1
+        ",
+    );
+
+    Ok(script
+        .key(claim_key(generation, shard_id))
+        .arg(process)
+        .arg(CLAIM_TTL)
+        .invoke_async::<bool>(&mut *redis.get().await?)
+        .await?)
+}
+
+// only releases the claim if it's still ours
+pub async fn release_claim(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    generation: u64,
+    shard_id: u32,
+    process: &str,
+) -> Result<(), Error> {
+    let script = Script::new(
+        r"
+        if redis.call('GET', KEYS[1]) == ARGV[1] then
+            redis.call('DEL', KEYS[1])
+        end
+        ",
+    );
+
+    script
+        .key(claim_key(generation, shard_id))
+        .arg(process)
+        .invoke_async::<()>(&mut *redis.get().await?)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn mark_ready(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    generation: u64,
+    shard_id: u32,
+) -> Result<(), Error> {
+    redis
+        .get()
+        .await?
+        .sadd::<String, u32, ()>(ready_key(generation), shard_id)
+        .await?;
+
+    Ok(())
+}
+
+// how many shards of a generation have been ready at some point
+pub async fn ready_count(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    generation: u64,
+) -> Result<u32, Error> {
+    Ok(redis
+        .get()
+        .await?
+        .scard::<String, u32>(ready_key(generation))
+        .await?)
+}
+
+// acknowledges a shard of the next generation is publishing while cutting over
+pub async fn mark_publishing(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    generation: u64,
+    shard_id: u32,
+) -> Result<(), Error> {
+    redis
+        .get()
+        .await?
+        .sadd::<String, u32, ()>(publishing_key(generation), shard_id)
+        .await?;
+
+    Ok(())
+}
+
+// how many shards of a generation acknowledged they're publishing
+pub async fn publishing_count(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    generation: u64,
+) -> Result<u32, Error> {
+    Ok(redis
+        .get()
+        .await?
+        .scard::<String, u32>(publishing_key(generation))
+        .await?)
+}
+
+// removes what's left of a generation that's no longer running
+pub async fn clear_generation(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    generation: u64,
+) -> Result<(), Error> {
+    redis
+        .get()
+        .await?
+        .del::<Vec<String>, ()>(vec![ready_key(generation), publishing_key(generation)])
+        .await?;
+
+    Ok(())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time went backwards")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shard_plan_test() {
+        let plan = ShardPlan::new(2);
+        assert!(plan.publishes(1));
+        assert!(!plan.retired(1));
+        assert_eq!(plan.joining().generation, 1);
+
+        // next generation starts up, the current one keeps publishing
+        let plan = plan.start_resharding(4);
+        assert_eq!(plan.joining().generation, 2);
+        assert_eq!(plan.joining().shard_count, 4);
+        assert!(plan.publishes(1));
+        assert!(!plan.publishes(2));
+        assert!(!plan.retired(1));
+        assert!(!plan.retired(2));
+
+        // next generation takes over, the current one keeps publishing until
+        // the plan is finished
+        let cut_over = plan.cut_over();
+        assert!(cut_over.publishes(1));
+        assert!(cut_over.publishes(2));
+        assert_eq!(cut_over.publishing().generation, 2);
+        assert!(!cut_over.retired(1));
+        assert!(!cut_over.retired(2));
+
+        let finished = cut_over.finish();
+        assert_eq!(finished.current.generation, 2);
+        assert_eq!(finished.current.shard_count, 4);
+        assert!(finished.publishes(2));
+        assert!(!finished.publishes(1));
+        assert!(finished.retired(1));
+
+        // aborted generations retire and never get reused
+        let aborted = plan.abort();
+        assert!(aborted.publishes(1));
+        assert!(aborted.retired(2));
+        assert_eq!(aborted.start_resharding(4).joining().generation, 3);
+    }
+}
@@ -2,11 +2,30 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
+// hash of `status_field` to `ShardState` json
+pub const SHARD_STATUS_KEY: &str = "tulpje:shard_status";
+
+// shards save their state at least every heartbeat, states that haven't been
+// saved in this long belong to a shard that's gone, in seconds
+pub const STALE_AFTER: u64 = 5 * 60;
+
+// shards of both generations run while resharding, so states are kept per
+// shard count
+pub fn status_field(shard_id: u32, shard_count: u32) -> String {
+    format!("{}:{}", shard_count, shard_id)
+}
+
 #[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
 pub struct ShardState {
     pub shard_id: u32,
+    pub shard_count: u32,
     pub guild_count: u64,
 
+    // metrics name of the process running the shard, and the host it runs on
+    pub process: String,
+    pub host: String,
+
     pub up: bool,
     pub disconnect_count: u64,
 
@@ -16,12 +35,16 @@ pub struct ShardState {
     pub last_started: u64,
     pub last_heartbeat: u64,
     pub last_connection: u64,
+    pub updated_at: u64,
 }
 
 impl ShardState {
-    pub fn new(shard_id: u32) -> Self {
+    pub fn new(shard_id: u32, shard_count: u32, process: String) -> Self {
         Self {
             shard_id,
+            shard_count,
+            process,
+            host: crate::hostname(),
             last_started: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .expect("time went backwards")
@@ -31,6 +54,18 @@ impl ShardState {
         }
     }
 
+    // whether the state should be removed, because the shard is gone or its
+    // shard count isn't running anymore
+    pub fn is_stale(&self, shard_counts: &[u32]) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_secs();
+
+        !shard_counts.contains(&self.shard_count)
+            || now.saturating_sub(self.updated_at) > STALE_AFTER
+    }
+
     // heuristic way to determine whether the shard is up,
     // no heartbeats in heartbeat_interval * 1.2 = down
     pub fn is_up(&self) -> bool {
@@ -60,26 +95,45 @@ mod tests {
     #[test]
     fn shard_state_is_up_test() {
         // if `up` is false we should be down
-        let state = ShardState::new(0);
+        let state = ShardState::new(0, 1, "gateway-0".into());
         assert!(!state.is_up());
 
         // if `up` is true but we have no heartbeat, should be down
-        let mut state = ShardState::new(0);
+        let mut state = ShardState::new(0, 1, "gateway-0".into());
         state.up = true;
         assert!(!state.is_up());
 
         // if `up` is true but we have no recent heartbeat, should be down
-        let mut state = ShardState::new(0);
+        let mut state = ShardState::new(0, 1, "gateway-0".into());
         state.up = true;
         state.last_heartbeat = unix_now() - 1_500;
         state.heartbeat_interval = 1_000;
         assert!(!state.is_up());
 
         // if `up` is true and we have recent heartbeat, should be up
-        let mut state = ShardState::new(0);
+        let mut state = ShardState::new(0, 1, "gateway-0".into());
         state.up = true;
         state.last_heartbeat = unix_now();
         state.heartbeat_interval = 1_000;
         assert!(state.is_up());
     }
+
+    #[test]
+    fn shard_state_is_stale_test() {
+        // states that were never saved are stale
+        let mut state = ShardState::new(2, 4, "gateway-2".into());
+        assert!(state.is_stale(&[4]));
+
+        state.updated_at = unix_now();
+        assert!(!state.is_stale(&[4]));
+        assert!(!state.is_stale(&[8, 4]));
+
+        // shards of a shard count that isn't running are stale
+        assert!(state.is_stale(&[8]));
+        assert!(state.is_stale(&[]));
+
+        // states that haven't been saved in a while are stale
+        state.updated_at = unix_now() - STALE_AFTER - 1;
+        assert!(state.is_stale(&[4]));
+    }
 }
@@ -0,0 +1,293 @@
+use std::error::Error;
+
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicGetOptions, BasicNackOptions, BasicPublishOptions,
+        ConfirmSelectOptions,
+    },
+    types::{AMQPValue, FieldTable},
+    BasicProperties, Channel, Connection, ConnectionProperties,
+};
+
+use tulpje_shared::{amqp::Headers, dead_letter, envelope, routing, DiscordEventMeta};
+
+struct DeadLetter {
+    delivery: Delivery,
+    headers: Headers,
+
+    error: Option<String>,
+    meta: Option<DiscordEventMeta>,
+    routing_key: Option<String>,
+    failed_at: Option<i64>,
+}
+
+impl DeadLetter {
+    fn new(delivery: Delivery) -> Self {
+        let headers = headers(&delivery);
+
+        let error = headers.get(dead_letter::HEADER_ERROR).cloned();
+        // events that couldn't be parsed only have their meta in the envelope
+        let meta = headers
+            .get(dead_letter::HEADER_META)
+            .and_then(|meta| serde_json::from_str(meta).ok())
+            .or_else(|| envelope::meta(&headers).ok());
+        let routing_key = headers.get(dead_letter::HEADER_ROUTING_KEY).cloned();
+        let failed_at = headers
+            .get(dead_letter::HEADER_FAILED_AT)
+            .and_then(|ts| ts.parse().ok());
+
+        Self {
+            delivery,
+            headers,
+            error,
+            meta,
+            routing_key,
+            failed_at,
+        }
+    }
+
+    fn uuid(&self) -> String {
+        self.meta
+            .as_ref()
+            .map_or_else(|| String::from("-"), |meta| meta.uuid.to_string())
+    }
+
+    fn failed_at(&self) -> String {
+        self.failed_at
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map_or_else(|| String::from("-"), |ts| ts.to_rfc3339())
+    }
+}
+
+// only keeps string headers
+fn headers(delivery: &Delivery) -> Headers {
+    delivery
+        .properties
+        .headers()
+        .as_ref()
+        .map(|field_table| {
+            field_table
+                .inner()
+                .iter()
+                .filter_map(|(key, value)| match value {
+                    AMQPValue::LongString(value) => Some((
+                        key.as_str().to_owned(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// the envelope headers of the original event, without the dead-letter ones
+fn replay_headers(headers: &Headers) -> FieldTable {
+    let dead_letter_headers = [
+        dead_letter::HEADER_ERROR,
+        dead_letter::HEADER_META,
+        dead_letter::HEADER_ROUTING_KEY,
+        dead_letter::HEADER_FAILED_AT,
+    ];
+
+    let mut field_table = FieldTable::default();
+    for (key, value) in headers
+        .iter()
+        .filter(|(key, _)| !dead_letter_headers.contains(&key.as_str()))
+    {
+        field_table.insert(
+            key.as_str().into(),
+            AMQPValue::LongString(value.clone().into()),
+        );
+    }
+
+    field_table
+}
+
+fn usage() -> ! {
+    println!("usage: dead-letter <command>");
+    println!();
+    println!("commands:");
+    println!("  list               list dead-lettered events");
+    println!("  inspect <uuid>     show the error, meta and payload of an event");
+    println!("  replay <uuid|all>  re-publish events onto the discord exchange");
+    std::process::exit(64);
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    // load .env into environment vars, ignore if not found
+    match dotenvy::dotenv().map(|_| ()) {
+        Err(err) if err.not_found() => eprintln!("warn: no .env file found"),
+        Err(err) => eprintln!("warn: error loading env vars: {}", err),
+        Ok(()) => (),
+    };
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, target) = match args.as_slice() {
+        [command] if command == "list" => (command.as_str(), None),
+        [command, target] if command == "inspect" || command == "replay" => {
+            (command.as_str(), Some(target.as_str()))
+        }
+        _ => usage(),
+    };
+
+    let addr = std::env::var("RABBITMQ_ADDRESS").map_err(|_| "RABBITMQ_ADDRESS not set")?;
+    let conn = Connection::connect(
+        &addr,
+        ConnectionProperties::default()
+            .with_executor(tokio_executor_trait::Tokio::current())
+            .with_reactor(tokio_reactor_trait::Tokio),
+    )
+    .await?;
+    let chan = conn.create_channel().await?;
+    // so we only remove events from the dead-letter queue once they're re-published
+    chan.confirm_select(ConfirmSelectOptions::default()).await?;
+
+    // AMQP has no way to peek at messages, so we get all of them without
+    // acking, anything we don't replay gets requeued at the end
+    let mut dead_letters = Vec::new();
+    while let Some(message) = chan
+        .basic_get(dead_letter::QUEUE, BasicGetOptions::default())
+        .await?
+    {
+        dead_letters.push(DeadLetter::new(message.delivery));
+    }
+
+    let result = match (command, target) {
+        ("list", _) => {
+            list(&dead_letters);
+            Ok(())
+        }
+        ("inspect", Some(uuid)) => {
+            inspect(&dead_letters, uuid);
+            Ok(())
+        }
+        ("replay", Some(target)) => replay(&chan, &dead_letters, target).await,
+        _ => usage(),
+    };
+
+    // requeue everything that wasn't replayed
+    for dead_letter in dead_letters
+        .iter()
+        .filter(|dead_letter| !dead_letter.delivery.acker.used())
+    {
+        dead_letter
+            .delivery
+            .acker
+            .nack(BasicNackOptions {
+                requeue: true,
+                ..Default::default()
+            })
+            .await?;
+    }
+    conn.close(0, "done").await?;
+
+    result
+}
+
+fn list(dead_letters: &[DeadLetter]) {
+    if dead_letters.is_empty() {
+        println!("no dead-lettered events");
+        return;
+    }
+
+    for dead_letter in dead_letters {
+        println!(
+            "{}  {}  shard {}  {} bytes  {}",
+            dead_letter.uuid(),
+            dead_letter.failed_at(),
+            dead_letter
+                .meta
+                .as_ref()
+                .map_or_else(|| String::from("-"), |meta| meta.shard.to_string()),
+            dead_letter.delivery.data.len(),
+            dead_letter.error.as_deref().unwrap_or("-"),
+        );
+    }
+    println!();
+    println!("{} dead-lettered event(s)", dead_letters.len());
+}
+
+fn inspect(dead_letters: &[DeadLetter], uuid: &str) {
+    let Some(dead_letter) = dead_letters
+        .iter()
+        .find(|dead_letter| dead_letter.uuid() == uuid)
+    else {
+        println!("no dead-lettered event with uuid {}", uuid);
+        return;
+    };
+
+    println!("uuid:      {}", dead_letter.uuid());
+    println!("failed at: {}", dead_letter.failed_at());
+    println!("error:     {}", dead_letter.error.as_deref().unwrap_or("-"));
+    println!("meta:      {:?}", dead_letter.meta);
+    println!(
+        "routing:   {}",
+        dead_letter.routing_key.as_deref().unwrap_or("-")
+    );
+    println!();
+
+    // compressed payloads are unreadable, so decode the envelope if we can
+    let payload = envelope::decode(&dead_letter.headers, &dead_letter.delivery.data).map_or_else(
+        |_| String::from_utf8_lossy(&dead_letter.delivery.data).into_owned(),
+        |event| event.payload,
+    );
+    match serde_json::from_str::<serde_json::Value>(&payload) {
+        Ok(json) => println!(
+            "{}",
+            serde_json::to_string_pretty(&json).unwrap_or_else(|_| payload.clone())
+        ),
+        Err(_) => println!("{}", payload),
+    }
+}
+
+async fn replay(
+    chan: &Channel,
+    dead_letters: &[DeadLetter],
+    target: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut replayed = 0_usize;
+
+    for dead_letter in dead_letters
+        .iter()
+        .filter(|dead_letter| target == "all" || dead_letter.uuid() == target)
+    {
+        // events dead-lettered before routing keys were recorded go straight
+        // to the shared queue
+        let (exchange, routing_key) = dead_letter
+            .routing_key
+            .as_deref()
+            .map_or(("", "discord"), |routing_key| {
+                (routing::EXCHANGE, routing_key)
+            });
+
+        let confirmation = chan
+            .basic_publish(
+                exchange,
+                routing_key,
+                BasicPublishOptions::default(),
+                &dead_letter.delivery.data,
+                BasicProperties::default().with_headers(replay_headers(&dead_letter.headers)),
+            )
+            .await?
+            .await?;
+
+        if !confirmation.is_ack() {
+            return Err(format!("rabbitmq didn't confirm replaying {}", dead_letter.uuid()).into());
+        }
+
+        dead_letter
+            .delivery
+            .acker
+            .ack(BasicAckOptions::default())
+            .await?;
+        println!("replayed {}", dead_letter.uuid());
+        replayed += 1;
+    }
+
+    println!("replayed {} event(s)", replayed);
+
+    Ok(())
+}